@@ -0,0 +1,390 @@
+//! `ftnt`, preprocess tweets from the command line.
+//!
+//! ```text
+//! ftnt tweets.txt -o clean.txt --urls remove --mentions tag
+//! cat tweets.jsonl | ftnt --format jsonl --field full_text --extra-fields hashtags,urls > clean.jsonl
+//! ftnt labels.csv --format csv --field tweet --config pipeline.toml
+//! ftnt --config sentiment --urls remove --dump-config toml > pipeline.toml
+//! ```
+//!
+//! The binary is built by the `cli` feature, e.g. `cargo install --path . --features cli`.
+//!
+//! `--config` is a preset, see `PrepOptions::preset`, or a TOML, JSON or YAML file of `PrepOptions`,
+//! the flags of the command line taking precedence over it.
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+
+use faster_tweet_nlp_toolkit::prep::compression::{create_output, open_input};
+use faster_tweet_nlp_toolkit::prep::config::{RetweetMode, SlangMode, Typography};
+use faster_tweet_nlp_toolkit::prep::file_format::{CsvColumn, CsvOptions, ExtraField, FileFormat, JsonlOptions};
+use faster_tweet_nlp_toolkit::prep::options::{ConfigFormat, PrepOptions, TokenizerOptions};
+use faster_tweet_nlp_toolkit::prep::text_prep::{prep_stream_with_options, FileOptions};
+use faster_tweet_nlp_toolkit::prep::token::ActionKind;
+use faster_tweet_nlp_toolkit::PrepError;
+
+#[cfg(test)]
+#[path = "../test_dir.rs"]
+mod test_dir;
+
+#[derive(Debug, Parser)]
+#[command(name = "ftnt", version, about = "Preprocess tweets, one per line or record", args_override_self = true)]
+struct Cli {
+    /// The input file, the standard input if missing or "-"
+    input: Option<String>,
+    /// The output file, the standard output if missing or "-"
+    #[arg(short, long)]
+    output: Option<String>,
+    /// A preset, e.g. "sentiment", or a TOML, JSON or YAML file of options, overridden by the flags
+    #[arg(long)]
+    config: Option<String>,
+    /// Print the options in this format instead of preprocessing, e.g. to record them
+    #[arg(long, value_enum)]
+    dump_config: Option<DumpFormat>,
+
+    /// The format of the input
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// The JSONL field, e.g. "retweeted_status.full_text", or the CSV column, by name or index
+    #[arg(long)]
+    field: Option<String>,
+    /// The JSONL field of the cleaned text, default to "clean_text"
+    #[arg(long)]
+    output_field: Option<String>,
+    /// Replace the text of the JSONL field instead of adding a field
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    replace: Option<bool>,
+    /// The fields added to the JSONL records: hashtags, mentions, urls or emojis
+    #[arg(long, value_delimiter = ',')]
+    extra_fields: Vec<ExtraField>,
+    /// The CSV delimiter, default to ',', or a tab for tsv
+    #[arg(long)]
+    delimiter: Option<char>,
+    /// Whether the CSV file starts with a header
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    header: Option<bool>,
+    /// The number of threads, default to the available parallelism
+    #[arg(long)]
+    threads: Option<usize>,
+    /// The number of lines given to a thread at once
+    #[arg(long)]
+    chunk_size: Option<usize>,
+
+    /// Re-encode the text, e.g. "ascii"
+    #[arg(long)]
+    encoding: Option<String>,
+    /// Remove the '�' left by an encoding error
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    remove_unencodable_char: Option<bool>,
+    /// The typographic variants to replace by their plain form, e.g. "quotes,apostrophes", "all" or "none",
+    /// among quotes, apostrophes, dashes, ellipsis, full_width and spaces
+    #[arg(long)]
+    typography: Option<Typography>,
+    /// Lowercase the text, default to true
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    to_lower: Option<bool>,
+    /// Strip the accents
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    strip_accents: Option<bool>,
+    /// Reduce the repeated characters
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    reduce_len: Option<bool>,
+    /// How many times --reduce-len lets a character be repeated
+    #[arg(long)]
+    max_repeat: Option<usize>,
+    /// Whether --reduce-len leaves the urls, emails, mentions and digits as they are, default to true
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    protect_tokens: Option<bool>,
+    /// A file of words, one per line, --reduce-len reduces the lengthened words to
+    #[arg(long)]
+    vocabulary: Option<String>,
+    /// What to do with the `RT @user: ` prefix of the retweets: keep, strip or tag, default to keep
+    #[arg(long)]
+    retweet: Option<RetweetMode>,
+    /// The contractions to expand: the built-in en and fr, or files of `contraction<TAB>expansion` lines
+    #[arg(long, value_delimiter = ',')]
+    contractions: Vec<String>,
+    /// The tokenizer, default to tweet
+    #[arg(long, value_enum)]
+    tokenizer: Option<TokenizerName>,
+    /// A regex matching the tokens, instead of --tokenizer
+    #[arg(long)]
+    token_pattern: Option<String>,
+    /// The tokens to filter out
+    #[arg(long, value_delimiter = ',')]
+    filters: Vec<String>,
+    /// The slang to normalize after the tokenization: the built-in en, or TSV or JSON files of expansions
+    #[arg(long, value_delimiter = ',')]
+    slang: Vec<String>,
+    /// What to do with a slang token: replace, append or annotate, default to replace
+    #[arg(long)]
+    slang_mode: Option<SlangMode>,
+    /// The stop words to remove: the built-in en, fr, es, de, it or pt, or files of words, one per line
+    #[arg(long, value_delimiter = ',')]
+    stopwords: Vec<String>,
+    /// Whether to keep the negations of the stop words, e.g. "not", default to false
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    keep_negations: Option<bool>,
+    /// A file of `word count` lines to split the hashtags with
+    #[arg(long)]
+    segmenter: Option<String>,
+    /// The action on the emojis
+    #[arg(long)]
+    emojis: Option<ActionKind>,
+    /// The action on the emoticons
+    #[arg(long)]
+    emoticons: Option<ActionKind>,
+    /// The action on the mentions
+    #[arg(long)]
+    mentions: Option<ActionKind>,
+    /// The action on the hashtags
+    #[arg(long)]
+    hashtags: Option<ActionKind>,
+    /// The action on the cashtags
+    #[arg(long)]
+    cashtags: Option<ActionKind>,
+    /// The action on the urls
+    #[arg(long)]
+    urls: Option<ActionKind>,
+    /// The action on the digits
+    #[arg(long)]
+    digits: Option<ActionKind>,
+    /// The action on the punctuations
+    #[arg(long)]
+    puncts: Option<ActionKind>,
+    /// The action on the emails
+    #[arg(long)]
+    emails: Option<ActionKind>,
+    /// The action on the HTML tags
+    #[arg(long)]
+    html_tags: Option<ActionKind>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DumpFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TokenizerName {
+    Tweet,
+    Whitespace,
+}
+
+impl Cli {
+    /// The options of the preset or config file, overridden by the flags.
+    fn options(&self) -> Result<PrepOptions, PrepError> {
+        let mut options = match &self.config {
+            Some(name) => PrepOptions::from_preset_or_file(name)?,
+            None => PrepOptions::default(),
+        };
+        if let Some(encoding) = &self.encoding {
+            options.encoding = Some(encoding.clone());
+        }
+        let flags = [
+            (self.remove_unencodable_char, &mut options.remove_unencodable_char),
+            (self.to_lower, &mut options.to_lower),
+            (self.strip_accents, &mut options.strip_accents),
+            (self.reduce_len, &mut options.reduce_len),
+            (self.protect_tokens, &mut options.protect_tokens),
+            (self.keep_negations, &mut options.keep_negations),
+        ];
+        for (value, option) in flags {
+            if let Some(value) = value {
+                *option = value;
+            }
+        }
+        options.max_repeat = self.max_repeat.unwrap_or(options.max_repeat);
+        options.typography = self.typography.unwrap_or(options.typography);
+        options.vocabulary = self.vocabulary.clone().or(options.vocabulary);
+        options.retweet = self.retweet.unwrap_or(options.retweet);
+        if !self.contractions.is_empty() {
+            options.contractions = self.contractions.clone();
+        }
+        options.segmenter = self.segmenter.clone().or(options.segmenter);
+        match (self.tokenizer, &self.token_pattern) {
+            (_, Some(pattern)) => options.tokenizer = TokenizerOptions::Regex(pattern.clone()),
+            (Some(TokenizerName::Tweet), None) => options.tokenizer = TokenizerOptions::Tweet,
+            (Some(TokenizerName::Whitespace), None) => options.tokenizer = TokenizerOptions::Whitespace,
+            (None, None) => {}
+        }
+        if !self.filters.is_empty() {
+            options.filters = self.filters.clone();
+        }
+        if !self.slang.is_empty() {
+            options.slang = self.slang.clone();
+        }
+        options.slang_mode = self.slang_mode.unwrap_or(options.slang_mode);
+        if !self.stopwords.is_empty() {
+            options.stopwords = self.stopwords.clone();
+        }
+        let actions = [
+            (self.emojis, &mut options.emojis),
+            (self.emoticons, &mut options.emoticons),
+            (self.mentions, &mut options.mentions),
+            (self.hashtags, &mut options.hashtags),
+            (self.cashtags, &mut options.cashtags),
+            (self.urls, &mut options.urls),
+            (self.digits, &mut options.digits),
+            (self.puncts, &mut options.puncts),
+            (self.emails, &mut options.emails),
+            (self.html_tags, &mut options.html_tags),
+        ];
+        for (action, option) in actions {
+            if action.is_some() {
+                *option = action;
+            }
+        }
+        Ok(options)
+    }
+
+    fn file_options(&self) -> FileOptions {
+        let format = match self.format {
+            Format::Text => FileFormat::Text,
+            Format::Jsonl => {
+                let default = JsonlOptions::default();
+                let field = self.field.clone().unwrap_or(default.field);
+                let output_field = match self.replace {
+                    Some(true) => None,
+                    _ => self.output_field.clone().or(default.output_field),
+                };
+                FileFormat::Jsonl(JsonlOptions { field, output_field, extra_fields: self.extra_fields.clone() })
+            }
+            Format::Csv | Format::Tsv => {
+                let default = if self.format == Format::Tsv { CsvOptions::tsv() } else { CsvOptions::default() };
+                let column = match &self.field {
+                    None => default.column,
+                    Some(field) => field.parse().map(CsvColumn::Index).unwrap_or_else(|_| CsvColumn::Name(field.clone())),
+                };
+                FileFormat::Csv(CsvOptions {
+                    delimiter: self.delimiter.unwrap_or(default.delimiter),
+                    header: self.header.unwrap_or(default.header),
+                    column,
+                })
+            }
+        };
+        let default = FileOptions::default();
+        FileOptions {
+            format,
+            threads: self.threads.unwrap_or(default.threads),
+            chunk_size: self.chunk_size.unwrap_or(default.chunk_size),
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), PrepError> {
+    let options = cli.options()?;
+    if let Some(format) = cli.dump_config {
+        let format = match format {
+            DumpFormat::Toml => ConfigFormat::Toml,
+            DumpFormat::Json => ConfigFormat::Json,
+            DumpFormat::Yaml => ConfigFormat::Yaml,
+        };
+        let config = options.to_string(format)?;
+        return writeln!(io::stdout(), "{}", config.trim_end()).map_err(|err| PrepError::io("<stdout>", err));
+    }
+    let config = options.build()?;
+    let options = cli.file_options();
+    let progress = |_: &_| true;
+    let input = cli.input.as_deref().filter(|&input| input != "-");
+    let reader = match input {
+        Some(path) => open_input(path)?,
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let input = (reader, input.unwrap_or("<stdin>"));
+    match cli.output.as_deref().filter(|&output| output != "-") {
+        Some(path) => {
+            let mut output = create_output(path)?;
+            let result = prep_stream_with_options(input, (&mut output, path), &config, &options, progress);
+            let finished = output.finish().map_err(|err| PrepError::io(path, err));
+            result.and(finished)
+        }
+        None => {
+            let mut writer = BufWriter::new(io::stdout().lock());
+            prep_stream_with_options(input, (&mut writer, "<stdout>"), &config, &options, progress)?;
+            writer.flush().map_err(|err| PrepError::io("<stdout>", err))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        // a closed pipe, e.g. `ftnt tweets.txt | head`, is not an error
+        Err(PrepError::Io { source, .. }) if source.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ftnt: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn _cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("ftnt").chain(args.iter().copied())).unwrap()
+    }
+
+    #[rstest]
+    #[case(&[], "hello @bob :) https://t.co/x")]
+    #[case(&["--urls", "remove", "--mentions=tag"], "hello <MENTION> :)")]
+    #[case(&["--to-lower", "false", "--emoticons", "remove", "--emoticons", "tag"], "Hello @Bob <EMOTICON> https://t.co/x")]
+    #[case(&["--tokenizer", "whitespace", "--filters", "hello,:)"], "@bob https://t.co/x")]
+    fn test_cli_config(#[case] args: &[&str], #[case] expected: &str) {
+        let config = _cli(args).options().unwrap().build().unwrap();
+        let text = "Hello @Bob :) https://t.co/x".to_string();
+        assert_eq!(faster_tweet_nlp_toolkit::prep::text_prep::prep_with_config(text, &config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cli_file_options() {
+        let options = _cli(&["--format", "tsv", "--field", "2", "--header=false", "--threads", "3"]).file_options();
+        let expected = FileFormat::Csv(CsvOptions { delimiter: '\t', header: false, column: CsvColumn::Index(2) });
+        assert_eq!((options.format, options.threads), (expected, 3));
+        let options = _cli(&["--format", "jsonl", "--replace", "--extra-fields", "urls,emojis"]).file_options();
+        let expected = FileFormat::Jsonl(JsonlOptions { output_field: None, extra_fields: vec![ExtraField::Urls, ExtraField::Emojis], ..JsonlOptions::default() });
+        assert_eq!(options.format, expected);
+        assert!(Cli::try_parse_from(["ftnt", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_options_config() {
+        let dir = TestDir::new("cli_options_config");
+        let path = dir.path("config.toml");
+        fs::write(&path, "urls = \"remove\"\nto_lower = false\n").unwrap();
+        let options = _cli(&["--config", &path, "--to-lower", "--mentions", "tag"]).options().unwrap();
+        let expected = PrepOptions { urls: Some(ActionKind::Remove), mentions: Some(ActionKind::Tag), ..PrepOptions::default() };
+        assert_eq!(options, expected);
+        let options = _cli(&["--config", "sentiment", "--hashtags", "remove"]).options().unwrap();
+        assert_eq!(options, PrepOptions { hashtags: Some(ActionKind::Remove), ..PrepOptions::preset("sentiment").unwrap() });
+        let options = _cli(&["--config", "topic-modeling", "--retweet", "keep"]).options().unwrap();
+        assert_eq!(options.retweet, RetweetMode::Keep);
+        let options = _cli(&["--config", "word-embeddings", "--typography", "quotes,dashes"]).options().unwrap();
+        assert_eq!(options.typography, Typography { quotes: true, dashes: true, ..Typography::default() });
+        let options = _cli(&["--contractions", "en,fr"]).options().unwrap();
+        assert_eq!(options.contractions, vec!["en", "fr"]);
+        let options = _cli(&["--slang", "en,slang.json", "--slang-mode", "append"]).options().unwrap();
+        assert_eq!((options.slang, options.slang_mode), (vec!["en".to_string(), "slang.json".to_string()], SlangMode::Append));
+        let options = _cli(&["--stopwords", "en,stopwords.txt", "--keep-negations"]).options().unwrap();
+        assert_eq!((options.stopwords, options.keep_negations), (vec!["en".to_string(), "stopwords.txt".to_string()], true));
+    }
+}
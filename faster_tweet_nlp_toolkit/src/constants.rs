@@ -1,11 +1,16 @@
-#![allow(dead_code, unused)]
-use std::{collections::{HashMap}, vec};
+#![allow(dead_code, unused, deprecated)]
+use std::collections::HashMap;
+
 use lazy_static::lazy_static;
+use crate::prep::token::TokenCondition;
 
 lazy_static! {
     pub static ref EMOJI_TAG: &'static str = "<EMOJI>";
     pub static ref MENTION_TAG: &'static str = "<MENTION>";
     pub static ref HASHTAG_TAG: &'static str = "<HASHTAG>";
+    pub static ref CASHTAG_TAG: &'static str = "<CASHTAG>";
+    /// The tag replacing the `RT @user: ` prefix of a retweet, never an HTML tag.
+    pub static ref RT_TAG: &'static str = "<RT>";
     pub static ref UNENCODABLE_CHAR: &'static str = "�";
     pub static ref URL_TAG: &'static str = "<URL>";
     pub static ref DIGIT_TAG: &'static str = "<DIGIT>";
@@ -30,26 +35,28 @@ lazy_static! {
         "\u{fe0e}",
         "\u{fe0f}",
     ];
-    pub static ref REPLACE_MAPPINGS: HashMap<&'static str, &'static str> = HashMap::from([
-        ("is_mention", *MENTION_TAG),
-        ("is_hashtag", *HASHTAG_TAG),
-        ("is_url", *URL_TAG),
-        ("is_digit", *DIGIT_TAG),
-        ("is_emoji", *EMOJI_TAG),
-        ("is_emoticon", *EMOTICON_TAG),
-        ("is_punct", *PUNCTUATION_TAG),
-        ("is_email", *EMAIL_TAG),
-    ]);
-    pub static ref ACTION_MAPPING: HashMap<&'static str, Vec<&'static str>> = HashMap::from([
-        ("is_mention", vec!["remove", "tag"]),
-        ("is_hashtag", vec!["remove", "tag"]),
-        ("is_url", vec!["remove", "tag"]),
-        ("is_digit", vec!["remove", "tag"]),
-        ("is_emoji", vec!["remove", "tag", "demojize", "emojize"]),
-        ("is_emoticon", vec!["remove", "tag"]),
-        ("is_punct", vec!["remove", "tag"]),
-        ("is_email", vec!["remove", "tag"]),
-        ("is_html_tag", vec!["remove"]),
-        ("is_stop_word", vec!["remove"]),
-    ]);
-}
\ No newline at end of file
+    /// The tag of each condition name that can be tagged, e.g. "is_mention" to "<MENTION>".
+    #[deprecated(note = "use `TokenCondition::tag` instead")]
+    pub static ref REPLACE_MAPPINGS: HashMap<&'static str, &'static str> = TokenCondition::ALL
+        .iter()
+        .filter_map(|condition| condition.tag().map(|tag| (condition.as_str(), tag)))
+        .collect();
+    /// The action names allowed for each condition name, e.g. "is_html_tag" to ["remove"].
+    #[deprecated(note = "use `TokenCondition::allowed_actions` and `ActionKind` instead")]
+    pub static ref ACTION_MAPPING: HashMap<&'static str, Vec<&'static str>> = TokenCondition::ALL
+        .iter()
+        .map(|condition| (condition.as_str(), condition.allowed_actions().iter().map(|action| action.as_str()).collect()))
+        .collect();
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_mappings() {
+        assert_eq!(REPLACE_MAPPINGS.get("is_mention"), Some(&"<MENTION>"));
+        assert_eq!(REPLACE_MAPPINGS.get("is_html_tag"), None);
+        assert_eq!(ACTION_MAPPING["is_emoji"], vec!["remove", "tag", "demojize", "emojize"]);
+        assert_eq!(ACTION_MAPPING["is_stop_word"], vec!["remove"]);
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+use crate::prep::token::ActionError;
+
+/// An error raised while preprocessing a text or a file.
+#[derive(Debug)]
+pub enum PrepError {
+    /// An unknown action, an unknown condition or an action not allowed for its condition.
+    Action(ActionError),
+    /// A PCRE2 error while matching, e.g. when the JIT stack or the match limit is exhausted.
+    Regex(pcre2::Error),
+    /// An I/O error on `path`, e.g. when the input file can't be opened.
+    Io { path: String, source: io::Error },
+    /// A text which is not valid UTF-8, `line` is the 1-based line number when reading a file.
+    Utf8 { line: Option<usize>, source: Utf8Error },
+    /// A malformed line of a data file read from `path`, `line` being its 1-based number.
+    Parse { path: String, line: usize, message: String },
+    /// A value which can't be written in a format, e.g. the options in TOML.
+    Serialize { format: &'static str, message: String },
+    /// The processing of a file was stopped by its progress callback.
+    Cancelled,
+}
+
+impl PrepError {
+    /// An `Io` error on the file `path`.
+    pub fn io(path: &str, source: io::Error) -> PrepError {
+        PrepError::Io { path: path.to_string(), source }
+    }
+}
+
+impl fmt::Display for PrepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrepError::Action(err) => write!(f, "{}", err),
+            PrepError::Regex(err) => write!(f, "{}", err),
+            PrepError::Io { path, source } => write!(f, "{}: {}", path, source),
+            PrepError::Utf8 { line: Some(line), source } => write!(f, "line {}: {}", line, source),
+            PrepError::Utf8 { line: None, source } => write!(f, "{}", source),
+            PrepError::Parse { path, line, message } => write!(f, "{}:{}: {}", path, line, message),
+            PrepError::Serialize { format, message } => write!(f, "cannot write {}: {}", format, message),
+            PrepError::Cancelled => write!(f, "cancelled by the progress callback"),
+        }
+    }
+}
+
+impl std::error::Error for PrepError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrepError::Action(err) => Some(err),
+            PrepError::Regex(err) => Some(err),
+            PrepError::Io { source, .. } => Some(source),
+            PrepError::Utf8 { source, .. } => Some(source),
+            PrepError::Parse { .. } | PrepError::Serialize { .. } | PrepError::Cancelled => None,
+        }
+    }
+}
+
+impl From<ActionError> for PrepError {
+    fn from(err: ActionError) -> PrepError {
+        PrepError::Action(err)
+    }
+}
+
+impl From<pcre2::Error> for PrepError {
+    fn from(err: pcre2::Error) -> PrepError {
+        PrepError::Regex(err)
+    }
+}
+
+impl From<FromUtf8Error> for PrepError {
+    fn from(err: FromUtf8Error) -> PrepError {
+        PrepError::Utf8 { line: None, source: err.utf8_error() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prep_error_display() {
+        let err = PrepError::io("tweets.txt", io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert_eq!(err.to_string(), "tweets.txt: not found");
+        let err = PrepError::from(String::from_utf8(vec![0xff]).unwrap_err());
+        assert_eq!(err.to_string(), "invalid utf-8 sequence of 1 bytes from index 0");
+        let err = PrepError::Parse { path: "unigrams.txt".to_string(), line: 3, message: "invalid count".to_string() };
+        assert_eq!(err.to_string(), "unigrams.txt:3: invalid count");
+        let err = PrepError::Serialize { format: "TOML", message: "unsupported None value".to_string() };
+        assert_eq!(err.to_string(), "cannot write TOML: unsupported None value");
+    }
+}
@@ -2,4 +2,8 @@
 #[macro_use]
 pub mod prep;
 pub mod constants;
-pub use prep::*;
\ No newline at end of file
+pub mod error;
+#[cfg(test)]
+mod test_dir;
+pub use prep::*;
+pub use error::PrepError;
\ No newline at end of file
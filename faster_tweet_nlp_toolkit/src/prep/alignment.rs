@@ -0,0 +1,256 @@
+use pcre2::bytes::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PrepError;
+use crate::prep::token::Span;
+
+/// The mapping table from a cleaned text back to the raw text it was preprocessed from.
+///
+/// Every byte of the cleaned text keeps the byte range of the raw text it comes from, so that a
+/// range of the cleaned text, e.g. a token, can be projected onto the raw tweet even after the
+/// preprocessing lowercased it, stripped its accents or decoded its HTML entities.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::text_parser::preprocess_text_aligned;
+/// let alignment = preprocess_text_aligned("Tom &amp; Jerry", &PrepConfig::default()).unwrap();
+/// let span = alignment.project(4, 11).unwrap();  // "& jerry" in "tom & jerry"
+/// // expect span.start == 4 and span.end == 15, i.e. "&amp; Jerry"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Alignment {
+    raw: String,
+    text: String,
+    /// The byte range of the raw text for every byte of `text`.
+    spans: Vec<(usize, usize)>,
+    /// The char offset of every byte offset of the raw text, end included.
+    raw_chars: Vec<usize>,
+}
+
+impl Alignment {
+    /// The raw text.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// The cleaned text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Project the byte range `start..end` of the cleaned text onto the raw text.
+    ///
+    /// Returns None if the range is out of bounds or doesn't fall on char boundaries.
+    pub fn project(&self, start: usize, end: usize) -> Option<Span> {
+        if start > end || end > self.text.len() || !self.text.is_char_boundary(start) || !self.text.is_char_boundary(end) {
+            return None;
+        }
+        let (start, end) = span_of(&self.spans, start, end);
+        Some(Span { start, end, char_start: self.raw_chars[start], char_end: self.raw_chars[end] })
+    }
+
+    /// Project the char range `char_start..char_end` of the cleaned text onto the raw text.
+    pub fn project_chars(&self, char_start: usize, char_end: usize) -> Option<Span> {
+        let byte_offset = |n: usize| {
+            self.text.char_indices().map(|(i, _)| i).chain(std::iter::once(self.text.len())).nth(n)
+        };
+        self.project(byte_offset(char_start)?, byte_offset(char_end)?)
+    }
+}
+
+/// The raw range covered by the bytes `start..end`, or an empty range where they would be.
+fn span_of(spans: &[(usize, usize)], start: usize, end: usize) -> (usize, usize) {
+    if start < end {
+        (spans[start].0, spans[end - 1].1)
+    } else if start < spans.len() {
+        (spans[start].0, spans[start].0)
+    } else {
+        spans.last().map(|&(_, end)| (end, end)).unwrap_or_default()
+    }
+}
+
+/// A text being preprocessed, which carries along the raw range of each of its bytes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AlignedText {
+    text: String,
+    spans: Vec<(usize, usize)>,
+}
+
+impl AlignedText {
+    pub(crate) fn new(raw: &str) -> AlignedText {
+        let mut spans = Vec::with_capacity(raw.len());
+        for (i, c) in raw.char_indices() {
+            spans.extend(std::iter::repeat_n((i, i + c.len_utf8()), c.len_utf8()));
+        }
+        AlignedText { text: raw.to_string(), spans }
+    }
+
+    pub(crate) fn with_capacity(capacity: usize) -> AlignedText {
+        AlignedText { text: String::with_capacity(capacity), spans: Vec::with_capacity(capacity) }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The raw range covered by the bytes `start..end` of the text.
+    pub(crate) fn span(&self, start: usize, end: usize) -> (usize, usize) {
+        span_of(&self.spans, start, end)
+    }
+
+    /// Append the bytes `start..end` of `source`, keeping their raw ranges.
+    pub(crate) fn push_slice(&mut self, source: &AlignedText, start: usize, end: usize) {
+        self.text.push_str(&source.text[start..end]);
+        self.spans.extend_from_slice(&source.spans[start..end]);
+    }
+
+    /// Append a new string coming from the raw range `span`.
+    pub(crate) fn push_str(&mut self, value: &str, span: (usize, usize)) {
+        self.text.push_str(value);
+        self.spans.extend(std::iter::repeat_n(span, value.len()));
+    }
+
+    /// Rewrite every char into the string written by `f`, which comes from the range of the char.
+    pub(crate) fn map_chars<F: FnMut(char, &mut String)>(&self, mut f: F) -> AlignedText {
+        let mut output = AlignedText::with_capacity(self.text.len());
+        let mut buffer = String::new();
+        for (i, c) in self.text.char_indices() {
+            let end = i + c.len_utf8();
+            buffer.clear();
+            f(c, &mut buffer);
+            if buffer == self.text[i..end] {
+                output.push_slice(self, i, end);
+            } else {
+                output.push_str(&buffer, self.span(i, end));
+            }
+        }
+        output
+    }
+
+    /// Lowercase the text like `str::to_lowercase`, which is char by char except for the final sigma.
+    pub(crate) fn to_lowercase(&self) -> AlignedText {
+        let lowercase = self.text.to_lowercase();
+        if lowercase == self.text {
+            return self.clone();
+        }
+        let mut output = AlignedText::with_capacity(lowercase.len());
+        let mut lowercase_chars = lowercase.chars();
+        for (i, c) in self.text.char_indices() {
+            let span = self.span(i, i + c.len_utf8());
+            for lower in lowercase_chars.by_ref().take(c.to_lowercase().count()) {
+                output.push_str(lower.encode_utf8(&mut [0; 4]), span);
+            }
+        }
+        output
+    }
+
+    /// Replace every match of `re`: `replace` is given the captures and writes the replacement
+    /// into the output, either with `push_slice` to keep the alignment of a group or with `push_str`.
+    pub(crate) fn replace_all<F>(&self, re: &Regex, mut replace: F) -> Result<AlignedText, PrepError>
+    where
+        F: FnMut(&Captures, &AlignedText, &mut AlignedText),
+    {
+        let mut output = AlignedText::with_capacity(self.text.len());
+        let mut last = 0;
+        for caps in re.captures_iter(self.text.as_bytes()) {
+            let caps = caps?;
+            let m = caps.get(0).unwrap();
+            output.push_slice(self, last, m.start());
+            replace(&caps, self, &mut output);
+            last = m.end();
+        }
+        output.push_slice(self, last, self.text.len());
+        Ok(output)
+    }
+
+    /// Decode the HTML entities like `html_escape::decode_html_entities`.
+    ///
+    /// An entity never contains a '&', so the text is decoded piece by piece, each piece
+    /// starting at a '&': only its head can be an entity, the rest being left as is.
+    pub(crate) fn decode_html_entities(&self) -> AlignedText {
+        if !self.text.contains('&') {
+            return self.clone();
+        }
+        let mut output = AlignedText::with_capacity(self.text.len());
+        let starts: Vec<usize> = self.text.match_indices('&').map(|(i, _)| i).collect();
+        output.push_slice(self, 0, starts[0]);
+        for (k, &start) in starts.iter().enumerate() {
+            let end = starts.get(k + 1).copied().unwrap_or(self.text.len());
+            let piece = &self.text[start..end];
+            let decoded = html_escape::decode_html_entities(piece);
+            if decoded == piece {
+                output.push_slice(self, start, end);
+                continue;
+            }
+            let kept: usize = piece.chars().rev().zip(decoded.chars().rev())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a.len_utf8())
+                .sum();
+            output.push_str(&decoded[..decoded.len() - kept], self.span(start, end - kept));
+            output.push_slice(self, end - kept, end);
+        }
+        output
+    }
+
+    /// Freeze the text into the `Alignment` with `raw`, the text it was preprocessed from.
+    pub(crate) fn into_alignment(self, raw: &str) -> Alignment {
+        let mut raw_chars = vec![0; raw.len() + 1];
+        let mut count = 0;
+        for (i, c) in raw.char_indices() {
+            raw_chars[i..i + c.len_utf8()].fill(count);
+            count += 1;
+        }
+        raw_chars[raw.len()] = count;
+        Alignment { raw: raw.to_string(), text: self.text, spans: self.spans, raw_chars }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn _raw_of<'a>(raw: &'a str, alignment: &Alignment, start: usize, end: usize) -> &'a str {
+        let span = alignment.project(start, end).unwrap();
+        &raw[span.start..span.end]
+    }
+
+    #[test]
+    fn test_aligned_text_to_lowercase() {
+        let raw = "ÉTÉ İs ΟΔΟΣ";
+        let text = AlignedText::new(raw).to_lowercase();
+        assert_eq!(text.as_str(), raw.to_lowercase());
+        let alignment = text.into_alignment(raw);
+        assert_eq!(_raw_of(raw, &alignment, 0, 5), "ÉTÉ");
+        assert_eq!(_raw_of(raw, &alignment, 6, 10), "İs");
+        assert_eq!(_raw_of(raw, &alignment, 11, 19), "ΟΔΟΣ");
+    }
+
+    #[rstest]
+    #[case("Tom &amp; Jerry", "tom & jerry", "&amp;")]
+    #[case("a &lt;3 b", "a <3 b", "&lt;3")]
+    #[case("x &amp;&GT; y", "x &> y", "&amp;&GT;")]
+    fn test_aligned_text_decode_html_entities(#[case] raw: &str, #[case] expected: &str, #[case] raw_token: &str) {
+        let text = AlignedText::new(raw).to_lowercase().decode_html_entities();
+        assert_eq!(text.as_str(), expected);
+        assert_eq!(text.as_str(), html_escape::decode_html_entities(&raw.to_lowercase()));
+        let alignment = text.into_alignment(raw);
+        let start = expected.find(' ').unwrap() + 1;
+        let end = expected[start..].find(' ').map(|i| start + i).unwrap_or(expected.len());
+        assert_eq!(_raw_of(raw, &alignment, start, end), raw_token);
+    }
+
+    #[test]
+    fn test_alignment_project() {
+        let raw = "Hé ÇA";
+        let alignment = AlignedText::new(raw).to_lowercase().into_alignment(raw);
+        assert_eq!(alignment.project(4, 7), Some(Span { start: 4, end: 7, char_start: 3, char_end: 5 }));
+        assert_eq!(alignment.project_chars(3, 5), alignment.project(4, 7));
+        assert_eq!(alignment.project(7, 7), Some(Span { start: 7, end: 7, char_start: 5, char_end: 5 }));
+        assert_eq!(alignment.project(2, 3), None);
+        assert_eq!(alignment.project(0, 8), None);
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::PrepError;
+
+/// The compression of a file read or written by `prep_file`.
+///
+/// Each format needs its cargo feature, `gzip`, `zstd` or `bzip2`, or all of them with `compression`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// The compression telling by the extension of `path`, e.g. `tweets.jsonl.gz`.
+    pub fn from_extension(path: &str) -> Compression {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("gz" | "gzip") => Compression::Gzip,
+            Some("zst" | "zstd") => Compression::Zstd,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::None,
+        }
+    }
+
+    /// The compression telling by the first bytes of a file.
+    pub fn from_magic_bytes(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        }
+    }
+
+    /// The error of a compression whose cargo feature is not enabled.
+    fn unsupported(&self, path: &str) -> PrepError {
+        let message = format!("{} support is not enabled, build with the `{}` feature", self.as_str(), self.as_str());
+        PrepError::io(path, io::Error::new(io::ErrorKind::Unsupported, message))
+    }
+}
+
+/// Open `path` for reading, decompressing it if its magic bytes, or else its extension, tell it is compressed.
+pub fn open_input(path: &str) -> Result<Box<dyn BufRead + Send>, PrepError> {
+    let mut reader = BufReader::new(File::open(path).map_err(|err| PrepError::io(path, err))?);
+    let compression = match Compression::from_magic_bytes(reader.fill_buf().map_err(|err| PrepError::io(path, err))?) {
+        Compression::None => Compression::from_extension(path),
+        compression => compression,
+    };
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(reader),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader).map_err(|err| PrepError::io(path, err))?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        #[allow(unreachable_patterns)]
+        compression => return Err(compression.unsupported(path)),
+    };
+    Ok(reader)
+}
+
+/// A file being written, compressed according to its extension.
+pub struct Output {
+    encoder: Encoder,
+}
+
+enum Encoder {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<BufWriter<File>>),
+}
+
+/// Create `path` for writing, compressing it if its extension tells so, e.g. `clean.txt.zst`.
+///
+/// The file is buffered, the output needs no other buffer.
+pub fn create_output(path: &str) -> Result<Output, PrepError> {
+    let compression = Compression::from_extension(path);
+    // checked before creating the file
+    let enabled = match compression {
+        Compression::None => true,
+        Compression::Gzip => cfg!(feature = "gzip"),
+        Compression::Zstd => cfg!(feature = "zstd"),
+        Compression::Bzip2 => cfg!(feature = "bzip2"),
+    };
+    if !enabled {
+        return Err(compression.unsupported(path));
+    }
+    let writer = BufWriter::new(File::create(path).map_err(|err| PrepError::io(path, err))?);
+    let encoder = match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(writer, flate2::Compression::default())),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, 0).map_err(|err| PrepError::io(path, err))?),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(writer, bzip2::Compression::default())),
+        _ => Encoder::Plain(writer),
+    };
+    Ok(Output { encoder })
+}
+
+impl Output {
+    /// Write the end of the compressed stream and flush the file.
+    pub fn finish(self) -> io::Result<()> {
+        #[allow(clippy::infallible_destructuring_match)]
+        let mut writer = match self.encoder {
+            Encoder::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[rstest]
+    #[case("tweets.jsonl.gz", Compression::Gzip)]
+    #[case("tweets.txt.zst", Compression::Zstd)]
+    #[case("tweets.txt.bz2", Compression::Bzip2)]
+    #[case("tweets.txt", Compression::None)]
+    #[case("gz", Compression::None)]
+    fn test_compression_from_extension(#[case] path: &str, #[case] expected: Compression) {
+        assert_eq!(Compression::from_extension(path), expected)
+    }
+
+    #[rstest]
+    #[case(&[0x1f, 0x8b, 0x08], Compression::Gzip)]
+    #[case(&[0x28, 0xb5, 0x2f, 0xfd, 0x00], Compression::Zstd)]
+    #[case(b"BZh91AY", Compression::Bzip2)]
+    #[case(b"hello", Compression::None)]
+    #[case(b"", Compression::None)]
+    fn test_compression_from_magic_bytes(#[case] bytes: &[u8], #[case] expected: Compression) {
+        assert_eq!(Compression::from_magic_bytes(bytes), expected)
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "gzip", case("output.txt.gz"))]
+    #[cfg_attr(feature = "zstd", case("output.txt.zst"))]
+    #[cfg_attr(feature = "bzip2", case("output.txt.bz2"))]
+    #[case("output.txt")]
+    fn test_output_then_input(#[case] name: &str) {
+        let dir = TestDir::new(&format!("compression_{}", name));
+        let path = &dir.path(name);
+        let mut output = create_output(path).unwrap();
+        output.write_all(b"hello\nworld\n").unwrap();
+        output.finish().unwrap();
+        // the magic bytes are enough, whatever the extension
+        let renamed = format!("{}.data", path);
+        fs::rename(path, &renamed).unwrap();
+        let mut contents = String::new();
+        open_input(&renamed).unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello\nworld\n");
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_output_unsupported() {
+        let dir = TestDir::new("compression_unsupported");
+        let path = dir.path("output.txt.gz");
+        let err = create_output(&path).err().unwrap();
+        assert!(err.to_string().ends_with("gzip support is not enabled, build with the `gzip` feature"));
+        assert!(!std::path::Path::new(&path).exists());
+    }
+}
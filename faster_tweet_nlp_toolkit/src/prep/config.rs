@@ -0,0 +1,570 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prep::contractions::Contractions;
+use crate::prep::segmenter::{Segmenter, DEFAULT_SEGMENTER};
+use crate::prep::slang::Slang;
+use crate::prep::stopwords::StopWords;
+use crate::prep::utils::named_enum;
+use crate::prep::token::{Action, ActionError, ActionKind, TokenCondition};
+use crate::prep::tokenizer::{Tokenizer, TweetTokenizer};
+
+/// The full set of options of a preprocessing pipeline.
+///
+/// A `PrepConfig` is built once with [`PrepConfigBuilder`] and can then be shared by
+/// `parse_text_with_config`, `prep_with_config`, `prep_file_with_config` and
+/// `ParsedText::process_with_config`. See `prep::options::PrepOptions` to save it to a file, load
+/// it back, or start from a preset.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::text_prep::prep_with_config;
+/// use faster_tweet_nlp_toolkit::prep::token::ActionKind;
+/// let config = PrepConfig::builder()
+///     .urls(ActionKind::Remove)
+///     .digits(ActionKind::Tag)
+///     .build()
+///     .unwrap();
+/// let result = prep_with_config(String::from("123 www.url.com"), &config).unwrap();  // expect "<DIGIT>"
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrepConfig {
+    /// How do we encode the text, e.g., "utf-8", default to None.
+    pub encoding: Option<String>,
+    /// Whether to remove the '�' left by an encoding error, default to false.
+    pub remove_unencodable_char: bool,
+    /// The typographic variants replaced by their plain form, e.g. "don’t" to "don't", default to none.
+    pub typography: Typography,
+    /// Whether to lowercase the text, default to true.
+    pub to_lower: bool,
+    /// Whether to strip the accents, default to false.
+    pub strip_accents: bool,
+    /// Whether to reduce repeated character sequences, default to false.
+    pub reduce_len: bool,
+    /// How many times `reduce_len` lets a char be repeated, at least 1, default to 3.
+    pub max_repeat: usize,
+    /// Whether `reduce_len` leaves the urls, emails, mentions and digits as they are, default to true.
+    pub protect_tokens: bool,
+    /// The words `reduce_len` reduces the lengthened words to, e.g. "goooood" to "good", default to None.
+    pub vocabulary: Option<Arc<HashSet<String>>>,
+    /// What to do with the `RT @user: ` prefix of a retweet, default to keep it.
+    pub retweet: RetweetMode,
+    /// The contractions expanded before the tokenization, e.g. "can't" to "can not", default to None.
+    pub contractions: Option<Arc<Contractions>>,
+    /// How to tokenize the text, default to `prep::tokenizer::TweetTokenizer`.
+    pub tokenizer: Arc<dyn Tokenizer>,
+    /// Tokens to filter out, default to empty.
+    pub filters: HashSet<String>,
+    /// The slang and abbreviations normalized after the tokenization, e.g. "idk", default to None.
+    pub slang: Option<Arc<Slang>>,
+    /// What the slang normalization does with a known token, default to replace it.
+    pub slang_mode: SlangMode,
+    /// The stop words to remove, matched whatever their case, default to None.
+    pub stopwords: Option<Arc<StopWords>>,
+    /// Whether to keep the negations of the stop words, e.g. "not", for sentiment analysis, default to false.
+    pub keep_negations: bool,
+    /// How to handle emojis, default to None.
+    pub emojis: Option<ActionKind>,
+    /// How to handle emoticons, default to None.
+    pub emoticons: Option<ActionKind>,
+    /// How to handle mentions, default to None.
+    pub mentions: Option<ActionKind>,
+    /// How to handle hashtags, default to None.
+    pub hashtags: Option<ActionKind>,
+    /// How to split the hashtags with the `Segment` action, default to the built-in English unigrams.
+    pub segmenter: Arc<Segmenter>,
+    /// How to handle cashtags like `$AAPL`, default to None.
+    pub cashtags: Option<ActionKind>,
+    /// How to handle urls, default to None.
+    pub urls: Option<ActionKind>,
+    /// How to handle digits, default to None.
+    pub digits: Option<ActionKind>,
+    /// How to handle punctuations, default to None.
+    pub puncts: Option<ActionKind>,
+    /// How to handle emails, default to None.
+    pub emails: Option<ActionKind>,
+    /// How to handle HTML tags, default to None.
+    pub html_tags: Option<ActionKind>,
+}
+
+impl Default for PrepConfig {
+    fn default() -> PrepConfig {
+        PrepConfig {
+            encoding: None,
+            remove_unencodable_char: false,
+            typography: Typography::default(),
+            to_lower: true,
+            strip_accents: false,
+            reduce_len: false,
+            max_repeat: 3,
+            protect_tokens: true,
+            vocabulary: None,
+            retweet: RetweetMode::Keep,
+            contractions: None,
+            tokenizer: Arc::new(TweetTokenizer),
+            filters: HashSet::new(),
+            slang: None,
+            slang_mode: SlangMode::Replace,
+            stopwords: None,
+            keep_negations: false,
+            emojis: None,
+            emoticons: None,
+            mentions: None,
+            hashtags: None,
+            segmenter: DEFAULT_SEGMENTER.clone(),
+            cashtags: None,
+            urls: None,
+            digits: None,
+            puncts: None,
+            emails: None,
+            html_tags: None,
+        }
+    }
+}
+
+impl PrepConfig {
+    /// Create a builder starting from the default options.
+    pub fn builder() -> PrepConfigBuilder {
+        PrepConfigBuilder::new()
+    }
+
+    /// The token actions of the config, in the order they are tried on each token.
+    pub fn actions(&self) -> Result<Vec<Action>, ActionError> {
+        [
+            (self.mentions, TokenCondition::Mention),
+            (self.hashtags, TokenCondition::Hashtag),
+            (self.cashtags, TokenCondition::Cashtag),
+            (self.urls, TokenCondition::Url),
+            (self.digits, TokenCondition::Digit),
+            (self.emojis, TokenCondition::Emoji),
+            (self.emoticons, TokenCondition::Emoticon),
+            (self.puncts, TokenCondition::Punct),
+            (self.emails, TokenCondition::Email),
+            (self.html_tags, TokenCondition::HtmlTag),
+            (self.stopwords.as_ref().map(|_| ActionKind::Remove), TokenCondition::StopWord),
+        ]
+        .into_iter()
+        .filter_map(|(kind, condition)| kind.map(|kind| Action::new(kind, condition)))
+        .map(|action| action.map(|action| action.with_segmenter(self.segmenter.clone())))
+        .collect()
+    }
+}
+
+/// What to do with the `RT @user: ` prefix of a retweet, e.g. "RT @bob: great game".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetweetMode {
+    /// Leave the prefix in the text, "rt" being a word and "@bob" a mention.
+    #[default]
+    Keep,
+    /// Remove the prefix.
+    Strip,
+    /// Replace the prefix by `<RT>`.
+    Tag,
+}
+
+named_enum!(RetweetMode, "retweet mode", { Keep => "keep", Strip => "strip", Tag => "tag" });
+
+/// What the slang normalization does with a known token, e.g. "idk".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlangMode {
+    /// Replace the token by its expansion, e.g. "i do not know".
+    #[default]
+    Replace,
+    /// Append the expansion to the token, e.g. "idk i do not know".
+    Append,
+    /// Leave the token as it is, its expansion being only set on `Token::expansion`.
+    Annotate,
+}
+
+named_enum!(SlangMode, "slang mode", { Replace => "replace", Append => "append", Annotate => "annotate" });
+
+/// The categories of typographic variants replaced by their plain form before the tokenization,
+/// so that e.g. "don’t" and "don't" are the same token.
+///
+/// All the categories are off by default, see `Typography::all`. As a string, e.g. for the command
+/// line, it is the list of its categories, e.g. "quotes,apostrophes", or "all", or "none".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Typography {
+    /// The quotes “ ” « » to '"'.
+    pub quotes: bool,
+    /// The apostrophes ‘ ’ to "'".
+    pub apostrophes: bool,
+    /// The hyphens, the dashes, e.g. – or —, and the minus sign to '-'.
+    pub dashes: bool,
+    /// The ellipsis char … to "...".
+    pub ellipsis: bool,
+    /// The full-width forms of the ASCII chars, e.g. ！ or Ａ, to the ASCII chars.
+    pub full_width: bool,
+    /// The non-breaking and the ideographic spaces to ' ', the zero-width spaces being removed.
+    pub spaces: bool,
+}
+
+impl Typography {
+    pub const CATEGORIES: [&'static str; 6] = ["quotes", "apostrophes", "dashes", "ellipsis", "full_width", "spaces"];
+
+    /// Every category on.
+    pub fn all() -> Typography {
+        Typography { quotes: true, apostrophes: true, dashes: true, ellipsis: true, full_width: true, spaces: true }
+    }
+
+    /// Whether every category is off.
+    pub fn is_none(&self) -> bool {
+        *self == Typography::default()
+    }
+
+    fn categories(&self) -> [(&'static str, bool); 6] {
+        [
+            ("quotes", self.quotes),
+            ("apostrophes", self.apostrophes),
+            ("dashes", self.dashes),
+            ("ellipsis", self.ellipsis),
+            ("full_width", self.full_width),
+            ("spaces", self.spaces),
+        ]
+    }
+
+    fn category_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "quotes" => Some(&mut self.quotes),
+            "apostrophes" => Some(&mut self.apostrophes),
+            "dashes" => Some(&mut self.dashes),
+            "ellipsis" => Some(&mut self.ellipsis),
+            "full_width" => Some(&mut self.full_width),
+            "spaces" => Some(&mut self.spaces),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Typography {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let categories: Vec<&str> = self.categories().into_iter().filter(|(_, on)| *on).map(|(name, _)| name).collect();
+        if categories.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", categories.join(","))
+        }
+    }
+}
+
+impl FromStr for Typography {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Typography, String> {
+        match s {
+            "all" => return Ok(Typography::all()),
+            "none" | "" => return Ok(Typography::default()),
+            _ => {}
+        }
+        let mut typography = Typography::default();
+        for name in s.split(',').map(str::trim) {
+            let category = typography.category_mut(name).ok_or_else(|| {
+                format!(r#"Unknown typography category "{}", expected one of {}, all, none"#, name, Typography::CATEGORIES.join(", "))
+            })?;
+            *category = true;
+        }
+        Ok(typography)
+    }
+}
+
+/// Parse an action name of the positional API, where an empty name means no action.
+///
+/// # Panics
+///
+/// Panics on an unknown action name, as the positional API has no way to report it, use
+/// `ActionKind::from_str` to get it as an `ActionError` instead.
+#[deprecated(note = "use `ActionKind::from_str` and the `PrepConfig` API instead")]
+pub(crate) fn parse_action_name(action: Option<&str>) -> Option<ActionKind> {
+    action
+        .filter(|name| !name.is_empty())
+        .map(|name| name.parse().unwrap_or_else(|err| panic!("{}", err)))
+}
+
+/// A builder for [`PrepConfig`].
+#[derive(Clone, Debug, Default)]
+pub struct PrepConfigBuilder {
+    config: PrepConfig,
+}
+
+impl PrepConfigBuilder {
+    /// Create a new builder with the default options.
+    pub fn new() -> PrepConfigBuilder {
+        PrepConfigBuilder::default()
+    }
+
+    /// Build the `PrepConfig`, failing if an action is not allowed for its kind of token.
+    pub fn build(&self) -> Result<PrepConfig, ActionError> {
+        self.config.actions()?;
+        Ok(self.config.clone())
+    }
+
+    /// Set the encoding used to re-encode the text, e.g., "utf-8".
+    pub fn encoding(&mut self, encoding: &str) -> &mut PrepConfigBuilder {
+        self.config.encoding = Some(encoding.to_string());
+        self
+    }
+
+    /// In case of encoding error of a character it is replaced with '�'. This option allows removing the '�'.
+    /// Otherwise a sequence of '�' is replaced by a single one.
+    pub fn remove_unencodable_char(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.remove_unencodable_char = yes;
+        self
+    }
+
+    /// Which typographic variants to replace by their plain form, e.g. `Typography::all()`.
+    pub fn typography(&mut self, typography: Typography) -> &mut PrepConfigBuilder {
+        self.config.typography = typography;
+        self
+    }
+
+    /// Whether to lowercase the text.
+    pub fn to_lower(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.to_lower = yes;
+        self
+    }
+
+    /// Whether to strip the accents.
+    pub fn strip_accents(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.strip_accents = yes;
+        self
+    }
+
+    /// Whether to reduce the chars repeated more than `max_repeat` times, e.g. "waaaaay" to "waaay".
+    pub fn reduce_len(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.reduce_len = yes;
+        self
+    }
+
+    /// How many times a char can be repeated when `reduce_len` is on, 0 being taken as 1 since a
+    /// char is always kept once, e.g. "sooooo" to "so".
+    pub fn max_repeat(&mut self, max_repeat: usize) -> &mut PrepConfigBuilder {
+        self.config.max_repeat = max_repeat;
+        self
+    }
+
+    /// Whether `reduce_len` leaves the urls, emails, mentions and digits as they are, e.g. "1000000".
+    pub fn protect_tokens(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.protect_tokens = yes;
+        self
+    }
+
+    /// The known words: `reduce_len` reduces a lengthened word to the first known word found by
+    /// keeping two or one of each repeated char, e.g. "sooooo goooood" to "so good".
+    /// See `prep::utils::read_word_list` to read them from a file.
+    pub fn vocabulary<I, S>(&mut self, words: I) -> &mut PrepConfigBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.vocabulary = Some(Arc::new(words.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// What to do with the `RT @user: ` prefix of a retweet, matched on the raw text.
+    pub fn retweet(&mut self, mode: RetweetMode) -> &mut PrepConfigBuilder {
+        self.config.retweet = mode;
+        self
+    }
+
+    /// The contractions to expand, e.g. `Contractions::builtin("en")`, matched whatever their case.
+    pub fn contractions<C: Into<Arc<Contractions>>>(&mut self, contractions: C) -> &mut PrepConfigBuilder {
+        self.config.contractions = Some(contractions.into());
+        self
+    }
+
+    /// How to tokenize the text: a `Tokenizer` or a function converting `String` to `Vec<Token>`.
+    pub fn tokenizer<T: Tokenizer + 'static>(&mut self, tokenizer: T) -> &mut PrepConfigBuilder {
+        self.config.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Tokens to filter out.
+    pub fn filters<I, S>(&mut self, filters: I) -> &mut PrepConfigBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.filters = filters.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The slang to normalize, e.g. `Slang::builtin("en")`, the mentions, hashtags, urls and emails
+    /// being left as they are.
+    pub fn slang<S: Into<Arc<Slang>>>(&mut self, slang: S) -> &mut PrepConfigBuilder {
+        self.config.slang = Some(slang.into());
+        self
+    }
+
+    /// What the slang normalization does with a known token: `Replace`, `Append` or `Annotate`.
+    pub fn slang_mode(&mut self, mode: SlangMode) -> &mut PrepConfigBuilder {
+        self.config.slang_mode = mode;
+        self
+    }
+
+    /// The stop words to remove, e.g. `StopWords::builtin("en")`, see also `keep_negations`.
+    pub fn stopwords<S: Into<Arc<StopWords>>>(&mut self, stopwords: S) -> &mut PrepConfigBuilder {
+        self.config.stopwords = Some(stopwords.into());
+        self
+    }
+
+    /// Whether to keep the negations of the stop words, e.g. "not" or "never", see `prep::stopwords::NEGATIONS`.
+    pub fn keep_negations(&mut self, yes: bool) -> &mut PrepConfigBuilder {
+        self.config.keep_negations = yes;
+        self
+    }
+
+    /// How to handle emojis: `Remove`, `Tag`, `Demojize` or `Emojize`.
+    pub fn emojis(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.emojis = Some(action);
+        self
+    }
+
+    /// How to handle emoticons: `Remove` or `Tag`.
+    pub fn emoticons(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.emoticons = Some(action);
+        self
+    }
+
+    /// How to handle mentions: `Remove` or `Tag`.
+    pub fn mentions(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.mentions = Some(action);
+        self
+    }
+
+    /// How to handle hashtags: `Remove`, `Tag` or `Segment`.
+    pub fn hashtags(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.hashtags = Some(action);
+        self
+    }
+
+    /// The segmenter splitting the hashtags, e.g. `Segmenter::from_file` with a unigram table.
+    pub fn segmenter(&mut self, segmenter: Segmenter) -> &mut PrepConfigBuilder {
+        self.config.segmenter = Arc::new(segmenter);
+        self
+    }
+
+    /// How to handle cashtags like `$AAPL`: `Remove` or `Tag`.
+    pub fn cashtags(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.cashtags = Some(action);
+        self
+    }
+
+    /// How to handle urls: `Remove` or `Tag`.
+    pub fn urls(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.urls = Some(action);
+        self
+    }
+
+    /// How to handle digits: `Remove` or `Tag`.
+    pub fn digits(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.digits = Some(action);
+        self
+    }
+
+    /// How to handle punctuations: `Remove` or `Tag`.
+    pub fn puncts(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.puncts = Some(action);
+        self
+    }
+
+    /// How to handle emails: `Remove` or `Tag`.
+    pub fn emails(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.emails = Some(action);
+        self
+    }
+
+    /// How to handle HTML tags like `<div>`: `Remove`.
+    pub fn html_tags(&mut self, action: ActionKind) -> &mut PrepConfigBuilder {
+        self.config.html_tags = Some(action);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_prep_config_default() {
+        let config = PrepConfig::default();
+        assert!(config.to_lower);
+        assert!(!config.strip_accents);
+        assert!(!config.reduce_len);
+        assert!(config.filters.is_empty());
+        assert_eq!(config.urls, None);
+        assert_eq!(config.retweet, RetweetMode::Keep);
+    }
+
+    #[rstest]
+    #[case("all", Typography::all())]
+    #[case("none", Typography::default())]
+    #[case("quotes, apostrophes", Typography { quotes: true, apostrophes: true, ..Typography::default() })]
+    #[case("full_width,spaces", Typography { full_width: true, spaces: true, ..Typography::default() })]
+    fn test_typography_from_str(#[case] s: &str, #[case] expected: Typography) {
+        let typography = s.parse::<Typography>().unwrap();
+        assert_eq!(typography, expected);
+        assert_eq!(typography.to_string().parse::<Typography>().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_typography_from_str_error() {
+        let err = "quotes,emojis".parse::<Typography>().unwrap_err();
+        assert_eq!(err, r#"Unknown typography category "emojis", expected one of quotes, apostrophes, dashes, ellipsis, full_width, spaces, all, none"#);
+    }
+
+    #[test]
+    fn test_retweet_mode_from_str() {
+        for mode in RetweetMode::ALL {
+            assert_eq!(mode.to_string().parse::<RetweetMode>(), Ok(mode));
+        }
+        assert_eq!("drop".parse::<RetweetMode>(), Err(r#"Unknown retweet mode "drop", expected one of keep, strip, tag"#.to_string()));
+    }
+
+    #[test]
+    fn test_slang_mode_from_str() {
+        for mode in SlangMode::ALL {
+            assert_eq!(mode.to_string().parse::<SlangMode>(), Ok(mode));
+        }
+        assert_eq!("expand".parse::<SlangMode>(), Err(r#"Unknown slang mode "expand", expected one of replace, append, annotate"#.to_string()));
+    }
+
+    #[test]
+    fn test_prep_config_builder() {
+        let config = PrepConfig::builder()
+            .to_lower(false)
+            .filters(["a", "b"])
+            .urls(ActionKind::Remove)
+            .emojis(ActionKind::Demojize)
+            .build()
+            .unwrap();
+        assert!(!config.to_lower);
+        assert_eq!(config.filters, HashSet::from(["a".to_string(), "b".to_string()]));
+        assert_eq!(config.urls, Some(ActionKind::Remove));
+        assert_eq!(config.emojis, Some(ActionKind::Demojize));
+        assert_eq!(config.mentions, None);
+        assert_eq!(config.actions().unwrap(), vec![
+            Action::new(ActionKind::Remove, TokenCondition::Url).unwrap(),
+            Action::new(ActionKind::Demojize, TokenCondition::Emoji).unwrap(),
+        ]);
+    }
+
+    #[test]
+    fn test_prep_config_builder_with_action_not_allowed() {
+        let result = PrepConfig::builder().hashtags(ActionKind::Emojize).build();
+        assert_eq!(result.unwrap_err(), ActionError::NotAllowed {
+            action: ActionKind::Emojize,
+            condition: TokenCondition::Hashtag,
+        });
+    }
+}
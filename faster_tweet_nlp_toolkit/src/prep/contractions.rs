@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::error::PrepError;
+use crate::prep::lexicon::Lexicon;
+use crate::prep::utils::{match_case, parse_expansions};
+
+lazy_static! {
+    /// The English contractions shipped with the crate, e.g. "can't" or "cant" to "can not".
+    pub static ref ENGLISH_CONTRACTIONS: Arc<Contractions> = Arc::new(
+        Contractions::parse("en_contractions.tsv", include_str!("../../data/en_contractions.tsv")).unwrap());
+    /// The French contractions shipped with the crate, e.g. "j'ai" to "je ai".
+    pub static ref FRENCH_CONTRACTIONS: Arc<Contractions> = Arc::new(
+        Contractions::parse("fr_contractions.tsv", include_str!("../../data/fr_contractions.tsv")).unwrap());
+}
+
+/// Expand contractions, e.g. "can't" to "can not" or "j'ai" to "je ai".
+///
+/// A contraction is either a whole word, e.g. "won't" or "cant", or an elided prefix ending with an
+/// apostrophe, e.g. "j'", which is expanded in front of any word. The contractions are matched
+/// whatever their case and their apostrophe, straight or curly, and the expansion takes the case
+/// of the contraction, e.g. "Can't" to "Can not" and "DON'T" to "DO NOT".
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::contractions::{Contractions, ENGLISH_CONTRACTIONS};
+/// use faster_tweet_nlp_toolkit::prep::lexicon::Lexicon;
+/// let expansion = ENGLISH_CONTRACTIONS.expand("Won’t");  // expect Some("Will not")
+/// let mut contractions = Contractions::builtin("fr").unwrap().as_ref().clone();
+/// contractions.extend([("l'", "le")]);
+/// let expansion = contractions.expand("l'homme");  // expect Some("le homme")
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contractions {
+    /// The expansion of each lowercase word, with straight apostrophes.
+    words: HashMap<String, String>,
+    /// The expansion of each lowercase elided prefix, longest first.
+    prefixes: Vec<(String, String)>,
+}
+
+impl Contractions {
+    /// Create the contractions from `(contraction, expansion)` pairs.
+    pub fn new<I, S, T>(mappings: I) -> Contractions
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: Into<String>,
+    {
+        let mut contractions = Contractions::default();
+        contractions.extend(mappings);
+        contractions
+    }
+
+    /// Add `(contraction, expansion)` pairs, replacing the expansion of a known contraction.
+    pub fn extend<I, S, T>(&mut self, mappings: I)
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: Into<String>,
+    {
+        for (contraction, expansion) in mappings {
+            let contraction = normalize(contraction.as_ref());
+            let expansion = expansion.into();
+            if contraction.ends_with('\'') {
+                self.prefixes.retain(|(prefix, _)| *prefix != contraction);
+                self.prefixes.push((contraction, expansion));
+            } else {
+                self.words.insert(contraction, expansion);
+            }
+        }
+        self.prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    }
+
+    fn parse(path: &str, contents: &str) -> Result<Contractions, PrepError> {
+        Ok(Contractions::new(parse_expansions(path, contents)?))
+    }
+
+    /// The expansion of a word, in the case of the word, or None if it is not a contraction.
+    pub fn expand(&self, word: &str) -> Option<String> {
+        let normalized = normalize(word);
+        if let Some(expansion) = self.words.get(&normalized) {
+            return Some(match_case(word, expansion));
+        }
+        let (prefix, expansion) = self.prefixes.iter()
+            .find(|(prefix, _)| normalized.len() > prefix.len() && normalized.starts_with(prefix.as_str()))?;
+        // the prefix is made of as many chars in the word, the apostrophe included
+        let split = word.char_indices().nth(prefix.chars().count()).map(|(i, _)| i)?;
+        Some(format!("{} {}", match_case(&word[..split], expansion), &word[split..]))
+    }
+}
+
+impl Lexicon for Contractions {
+    const LANGUAGES: &'static [&'static str] = &["en", "fr"];
+
+    /// The built-in table of a language, "en" or "fr".
+    fn builtin(language: &str) -> Option<Arc<Contractions>> {
+        match language {
+            "en" => Some(ENGLISH_CONTRACTIONS.clone()),
+            "fr" => Some(FRENCH_CONTRACTIONS.clone()),
+            _ => None,
+        }
+    }
+
+    /// Load the contractions of a file with one contraction and its expansion per line, separated by
+    /// a tab, or of a `.json` file of an object of expansions.
+    fn from_file(path: &str) -> Result<Contractions, PrepError> {
+        let contents = fs::read_to_string(path).map_err(|err| PrepError::io(path, err))?;
+        Contractions::parse(path, &contents)
+    }
+
+    /// Add the contractions of `other`, which replace the known ones.
+    fn merge(&mut self, other: &Contractions) {
+        let words = other.words.iter().map(|(word, expansion)| (word, expansion.clone()));
+        let prefixes = other.prefixes.iter().map(|(prefix, expansion)| (prefix, expansion.clone()));
+        self.extend(words.chain(prefixes));
+    }
+}
+
+/// A contraction lowercased and with straight apostrophes.
+fn normalize(contraction: &str) -> String {
+    contraction.to_lowercase().replace('’', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("can't", Some("can not"))]
+    #[case("Can’t", Some("Can not"))]
+    #[case("DON'T", Some("DO NOT"))]
+    #[case("I'm", Some("I am"))]
+    #[case("im", Some("i am"))]
+    #[case("cant", Some("can not"))]
+    #[case("couldn't've", Some("could not have"))]
+    #[case("John's", None)]
+    #[case("can", None)]
+    fn test_expand_english(#[case] word: &str, #[case] expected: Option<&str>) {
+        assert_eq!(ENGLISH_CONTRACTIONS.expand(word).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("j'ai", Some("je ai"))]
+    #[case("J’ai", Some("Je ai"))]
+    #[case("qu'il", Some("que il"))]
+    #[case("jusqu'ici", Some("jusque ici"))]
+    #[case("s'il", Some("si il"))]
+    #[case("s'appelle", Some("se appelle"))]
+    #[case("cest", Some("ce est"))]
+    #[case("j'", None)]
+    #[case("aujourd'hui", None)]
+    fn test_expand_french(#[case] word: &str, #[case] expected: Option<&str>) {
+        assert_eq!(FRENCH_CONTRACTIONS.expand(word).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_extend_and_merge() {
+        let mut contractions = Contractions::new([("gimme", "give me"), ("l'", "la")]);
+        contractions.extend([("l'", "le")]);
+        contractions.merge(&FRENCH_CONTRACTIONS);
+        assert_eq!(contractions.expand("Gimme").as_deref(), Some("Give me"));
+        assert_eq!(contractions.expand("l'homme").as_deref(), Some("le homme"));
+        assert_eq!(contractions.expand("j'ai").as_deref(), Some("je ai"));
+        let mut english = ENGLISH_CONTRACTIONS.as_ref().clone();
+        english.extend([("can't", "cannot")]);
+        assert_eq!(english.expand("can't").as_deref(), Some("cannot"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let contractions = Contractions::parse("contractions.json", r#"{"gonna": "going to", "l'": "le"}"#).unwrap();
+        assert_eq!(contractions, Contractions::new([("gonna", "going to"), ("l'", "le")]));
+    }
+}
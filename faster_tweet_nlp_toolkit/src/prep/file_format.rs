@@ -0,0 +1,386 @@
+use serde_json::{Map, Value};
+
+use crate::error::PrepError;
+use crate::prep::config::PrepConfig;
+use crate::prep::text_parser::{parse_text_with_config, ParsedText};
+use crate::prep::text_prep::prep_with_config;
+use crate::prep::utils::named_enum;
+
+/// The format of the files of `prep_file_with_options`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    /// One text per line.
+    #[default]
+    Text,
+    /// One JSON object per line, the text being one of its fields, see [`JsonlOptions`].
+    Jsonl(JsonlOptions),
+    /// CSV or TSV records, the text being one of their columns, see [`CsvOptions`].
+    Csv(CsvOptions),
+}
+
+/// Where the text of a JSONL record is, and where its cleaned version goes.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::file_format::{ExtraField, FileFormat, JsonlOptions};
+/// let format = FileFormat::Jsonl(JsonlOptions {
+///     field: "retweeted_status.full_text".to_string(),
+///     output_field: Some("clean_text".to_string()),
+///     extra_fields: vec![ExtraField::Hashtags],
+/// });
+/// let record = format.process(r#"{"id": 1, "retweeted_status": {"full_text": "Hello #NLP"}}"#.to_string(), &PrepConfig::default(), ("tweets.jsonl", 1)).unwrap();
+/// // expect {"id":1,"retweeted_status":{"full_text":"Hello #NLP"},"clean_text":"hello #nlp","hashtags":["#nlp"]}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonlOptions {
+    /// The path of the field to clean, the keys of the nested objects joined by dots, default to "text".
+    pub field: String,
+    /// The path of the field receiving the cleaned text, None to replace the original text,
+    /// default to "clean_text".
+    pub output_field: Option<String>,
+    /// The fields computed from the parsed text added to each record, default to none.
+    pub extra_fields: Vec<ExtraField>,
+}
+
+impl Default for JsonlOptions {
+    fn default() -> JsonlOptions {
+        JsonlOptions {
+            field: "text".to_string(),
+            output_field: Some("clean_text".to_string()),
+            extra_fields: Vec::new(),
+        }
+    }
+}
+
+/// The delimiter, header and text column of CSV records, quoted as in RFC 4180.
+///
+/// A quoted field may contain delimiters, doubled quotes and newlines, so a record can span several
+/// lines. The cleaned text replaces the text column, the other columns being kept.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::file_format::{CsvColumn, CsvOptions, FileFormat};
+/// let options = CsvOptions { column: CsvColumn::Name("tweet".to_string()), ..CsvOptions::default() };
+/// let options = options.with_header("id,tweet,label").unwrap();  // the column is now `CsvColumn::Index(1)`
+/// let record = FileFormat::Csv(options).process("7,\"Hi, \"\"you\"\"\",1".to_string(), &PrepConfig::default(), ("labels.csv", 2)).unwrap();
+/// // expect 7,"hi , "" you """,1
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The field delimiter, default to ','.
+    pub delimiter: char,
+    /// Whether the first record is a header, written as is, default to true.
+    pub header: bool,
+    /// The column of the text, default to the one named "text".
+    pub column: CsvColumn,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions { delimiter: ',', header: true, column: CsvColumn::Name("text".to_string()) }
+    }
+}
+
+/// A column of a CSV file, by its name in the header or by its 0-based index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CsvColumn {
+    Name(String),
+    Index(usize),
+}
+
+impl CsvOptions {
+    /// The default options of a TSV file.
+    pub fn tsv() -> CsvOptions {
+        CsvOptions { delimiter: '\t', ..CsvOptions::default() }
+    }
+
+    /// The options with the column name resolved into its index in `header`.
+    pub fn with_header(&self, header: &str) -> Result<CsvOptions, String> {
+        let CsvColumn::Name(name) = &self.column else {
+            return Ok(self.clone());
+        };
+        let index = parse_csv_record(header, self.delimiter)?.iter().position(|column| column == name)
+            .ok_or_else(|| format!(r#"no column "{}" in the header"#, name))?;
+        Ok(CsvOptions { column: CsvColumn::Index(index), ..self.clone() })
+    }
+}
+
+/// A list of tokens of the parsed text added to a record, under its name, e.g. "hashtags".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExtraField {
+    Hashtags,
+    Mentions,
+    Urls,
+    Emojis,
+}
+
+named_enum!(ExtraField, "field", { Hashtags => "hashtags", Mentions => "mentions", Urls => "urls", Emojis => "emojis" });
+
+impl ExtraField {
+    fn values(&self, parsed_text: &ParsedText) -> Vec<String> {
+        match self {
+            ExtraField::Hashtags => parsed_text.hashtags(),
+            ExtraField::Mentions => parsed_text.mentions(),
+            ExtraField::Urls => parsed_text.urls(),
+            ExtraField::Emojis => parsed_text.emojis(),
+        }
+    }
+}
+
+impl FileFormat {
+    /// Preprocess a line, `position` being the file and the 1-based line number it comes from.
+    pub fn process(&self, line: String, config: &PrepConfig, position: (&str, usize)) -> Result<String, PrepError> {
+        match self {
+            FileFormat::Text => prep_with_config(line, config),
+            FileFormat::Jsonl(options) => options.process(line, config, position),
+            FileFormat::Csv(options) => options.process(&line, config, position),
+        }
+    }
+}
+
+impl JsonlOptions {
+    /// Preprocess a JSONL record, a record without the text field being left as is.
+    fn process(&self, line: String, config: &PrepConfig, (path, line_number): (&str, usize)) -> Result<String, PrepError> {
+        let error = |message: String| PrepError::Parse { path: path.to_string(), line: line_number, message };
+        let mut record: Value = serde_json::from_str(&line).map_err(|err| error(err.to_string()))?;
+        let Some(text) = get_path(&record, &self.field).and_then(Value::as_str) else {
+            return Ok(line);
+        };
+        let mut parsed_text = parse_text_with_config(text.to_string(), config)?;
+        let clean_text = Value::String(parsed_text.value().to_string());
+        set_path(&mut record, self.output_field.as_deref().unwrap_or(&self.field), clean_text).map_err(error)?;
+        if let Some(record) = record.as_object_mut() {
+            for field in &self.extra_fields {
+                record.insert(field.as_str().to_string(), field.values(&parsed_text).into());
+            }
+        }
+        serde_json::to_string(&record).map_err(|err| error(err.to_string()))
+    }
+}
+
+impl CsvOptions {
+    /// Preprocess the text column of a CSV record, whose column name must be resolved with `with_header`.
+    fn process(&self, record: &str, config: &PrepConfig, (path, line_number): (&str, usize)) -> Result<String, PrepError> {
+        let error = |message: String| PrepError::Parse { path: path.to_string(), line: line_number, message };
+        let index = match &self.column {
+            CsvColumn::Index(index) => *index,
+            CsvColumn::Name(name) => return Err(error(format!(r#"the index of the column "{}" is unknown without the header"#, name))),
+        };
+        let mut fields = parse_csv_record(record, self.delimiter).map_err(error)?;
+        let n_fields = fields.len();
+        let Some(text) = fields.get_mut(index) else {
+            return Err(error(format!("no column {} in a record of {} fields", index, n_fields)));
+        };
+        *text = prep_with_config(std::mem::take(text), config)?;
+        Ok(write_csv_record(&fields, self.delimiter))
+    }
+}
+
+/// Where the CSV record being read is, to tell whether a newline ends it or is within a quoted field.
+///
+/// A quote opens a quoted field only at the start of a field, as in `parse_csv_record`, so a
+/// stray quote like `5" screen` doesn't swallow the following lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum CsvQuoting {
+    #[default]
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// After a quote in a quoted field, which ends the field unless another quote follows.
+    QuoteInQuoted,
+}
+
+impl CsvQuoting {
+    /// Read the next line of the record, return whether the record ends with it.
+    pub(crate) fn read_line(&mut self, line: &str, delimiter: char) -> bool {
+        for c in line.chars() {
+            *self = match (*self, c) {
+                (CsvQuoting::FieldStart, '"') => CsvQuoting::Quoted,
+                (CsvQuoting::Quoted, '"') => CsvQuoting::QuoteInQuoted,
+                (CsvQuoting::Quoted, _) | (CsvQuoting::QuoteInQuoted, '"') => CsvQuoting::Quoted,
+                (_, c) if c == delimiter => CsvQuoting::FieldStart,
+                _ => CsvQuoting::Unquoted,
+            };
+        }
+        *self != CsvQuoting::Quoted
+    }
+}
+
+/// Split a CSV record into its fields, unquoting the quoted ones.
+fn parse_csv_record(record: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".to_string()),
+                }
+            }
+            match chars.next() {
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                }
+                Some(c) if c == delimiter => fields.push(field),
+                Some(c) => return Err(format!(r#"unexpected "{}" after a quoted field"#, c)),
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    Some(c) if c == delimiter => break,
+                    Some(c) => field.push(c),
+                    None => {
+                        fields.push(field);
+                        return Ok(fields);
+                    }
+                }
+            }
+            fields.push(field);
+        }
+    }
+}
+
+/// Join fields into a CSV record, quoting those containing the delimiter, a quote or a newline.
+fn write_csv_record(fields: &[String], delimiter: char) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| {
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.clone()
+        }
+    }).collect();
+    fields.join(&delimiter.to_string())
+}
+
+/// The value at the dotted `path` of `value`, e.g. "retweeted_status.full_text".
+fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+/// Set the value at the dotted `path`, creating the missing objects on the way.
+fn set_path(value: &mut Value, path: &str, new_value: Value) -> Result<(), String> {
+    let mut keys = path.split('.').peekable();
+    let mut current = value;
+    while let Some(key) = keys.next() {
+        let Some(object) = current.as_object_mut() else {
+            return Err(format!(r#"can't set "{}", "{}" is not in an object"#, path, key));
+        };
+        if keys.peek().is_none() {
+            object.insert(key.to_string(), new_value);
+            return Ok(());
+        }
+        current = object.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::prep::token::ActionKind;
+
+    fn _process(options: JsonlOptions, line: &str) -> Result<String, PrepError> {
+        let config = PrepConfig::builder().urls(ActionKind::Remove).build().unwrap();
+        FileFormat::Jsonl(options).process(line.to_string(), &config, ("tweets.jsonl", 7))
+    }
+
+    #[rstest]
+    #[case("text", Some("clean_text"), r#"{"text":"Hi https://t.co/x","id":2}"#, r#"{"text":"Hi https://t.co/x","id":2,"clean_text":"hi"}"#)]
+    #[case("text", None, r#"{"text":"Hi https://t.co/x","id":2}"#, r#"{"text":"hi","id":2}"#)]
+    #[case("retweeted_status.full_text", Some("clean.text"), r#"{"retweeted_status":{"full_text":"YES"}}"#, r#"{"retweeted_status":{"full_text":"YES"},"clean":{"text":"yes"}}"#)]
+    #[case("retweeted_status.full_text", None, r#"{"retweeted_status":{"full_text":"YES"}}"#, r#"{"retweeted_status":{"full_text":"yes"}}"#)]
+    #[case("text", Some("clean_text"), r#"{"delete":{"id":3}}"#, r#"{"delete":{"id":3}}"#)]
+    #[case("text", Some("clean_text"), r#"{"text":12}"#, r#"{"text":12}"#)]
+    #[case("text", Some("clean.text"), r#"{"text":"hi","clean_text":3}"#, r#"{"text":"hi","clean_text":3,"clean":{"text":"hi"}}"#)]
+    fn test_jsonl_process(#[case] field: &str, #[case] output_field: Option<&str>, #[case] line: &str, #[case] expected: &str) {
+        let options = JsonlOptions { field: field.to_string(), output_field: output_field.map(String::from), ..JsonlOptions::default() };
+        assert_eq!(_process(options, line).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_jsonl_process_extra_fields() {
+        let options = JsonlOptions { extra_fields: ExtraField::ALL.to_vec(), ..JsonlOptions::default() };
+        let result = _process(options, r#"{"text":"@Bob #Rust 🦀 https://t.co/x"}"#).unwrap();
+        assert_eq!(result, r##"{"text":"@Bob #Rust 🦀 https://t.co/x","clean_text":"@bob #rust 🦀","hashtags":["#rust"],"mentions":["@bob"],"urls":[],"emojis":["🦀"]}"##);
+    }
+
+    #[rstest]
+    #[case(r#"{"text": "unterminated}"#, "tweets.jsonl:7: EOF while parsing a string at line 1 column 23")]
+    #[case(r#"{"text":"hi","clean":"x"}"#, r#"tweets.jsonl:7: can't set "clean.text", "text" is not in an object"#)]
+    fn test_jsonl_process_errors(#[case] line: &str, #[case] expected: &str) {
+        let options = JsonlOptions { output_field: Some("clean.text".to_string()), ..JsonlOptions::default() };
+        assert_eq!(_process(options, line).unwrap_err().to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("a,b,c", ',', vec!["a", "b", "c"])]
+    #[case("a\t\tc", '\t', vec!["a", "", "c"])]
+    #[case(r#""a, ""b""",c,"#, ',', vec![r#"a, "b""#, "c", ""])]
+    #[case("\"multi\nline\",x", ',', vec!["multi\nline", "x"])]
+    #[case("", ',', vec![""])]
+    #[case(r#"a"b,c"#, ',', vec![r#"a"b"#, "c"])]
+    fn test_parse_csv_record(#[case] record: &str, #[case] delimiter: char, #[case] expected: Vec<&str>) {
+        assert_eq!(parse_csv_record(record, delimiter).unwrap(), expected);
+        if !record.contains("a\"b") {
+            let fields: Vec<String> = expected.iter().map(|field| field.to_string()).collect();
+            assert_eq!(parse_csv_record(&write_csv_record(&fields, delimiter), delimiter).unwrap(), expected);
+        }
+    }
+
+    #[rstest]
+    #[case(&["a,b\n"], vec![true])]
+    #[case(&["5\" screen,b\n", "c,d\n"], vec![true, true])]
+    #[case(&["a,\"multi\n", "line, \"\"x\"\"\",b\n"], vec![false, true])]
+    #[case(&["a,\"\"\n"], vec![true])]
+    #[case(&["\"a\"\"\n", "b\"\n"], vec![false, true])]
+    fn test_csv_quoting(#[case] lines: &[&str], #[case] expected: Vec<bool>) {
+        let mut quoting = CsvQuoting::default();
+        let ends: Vec<bool> = lines.iter().map(|line| quoting.read_line(line, ',')).collect();
+        assert_eq!(ends, expected);
+        // Records strips the newline ending the record
+        assert!(parse_csv_record(lines.concat().strip_suffix('\n').unwrap(), ',').is_ok());
+    }
+
+    #[rstest]
+    #[case(r#""unterminated,x"#, "unterminated quoted field")]
+    #[case(r#""a"b,c"#, r#"unexpected "b" after a quoted field"#)]
+    fn test_parse_csv_record_errors(#[case] record: &str, #[case] expected: &str) {
+        assert_eq!(parse_csv_record(record, ',').unwrap_err(), expected);
+    }
+
+    #[rstest]
+    #[case(CsvColumn::Index(1), "1,\"Hi, https://t.co/x\n@Bob\",pos", Ok("1,\"hi , @bob\",pos"))]
+    #[case(CsvColumn::Index(1), "1,\"Say \"\"Hi\"\"\",pos", Ok(r#"1,"say "" hi """,pos"#))]
+    #[case(CsvColumn::Index(3), "1,Hi,pos", Err("labels.csv:7: no column 3 in a record of 3 fields"))]
+    #[case(CsvColumn::Name("text".to_string()), "1,Hi,pos", Err(r#"labels.csv:7: the index of the column "text" is unknown without the header"#))]
+    fn test_csv_process(#[case] column: CsvColumn, #[case] record: &str, #[case] expected: Result<&str, &str>) {
+        let config = PrepConfig::builder().urls(ActionKind::Remove).build().unwrap();
+        let format = FileFormat::Csv(CsvOptions { column, ..CsvOptions::default() });
+        let result = format.process(record.to_string(), &config, ("labels.csv", 7));
+        assert_eq!(result.as_deref().map_err(|err| err.to_string()), expected.map_err(String::from));
+    }
+
+    #[test]
+    fn test_csv_with_header() {
+        let options = CsvOptions { column: CsvColumn::Name("tweet".to_string()), ..CsvOptions::tsv() };
+        assert_eq!(options.with_header("id\ttweet").unwrap().column, CsvColumn::Index(1));
+        assert_eq!(options.with_header("id\ttext").unwrap_err(), r#"no column "tweet" in the header"#);
+    }
+
+    #[test]
+    fn test_extra_field_from_str() {
+        assert_eq!("urls".parse::<ExtraField>(), Ok(ExtraField::Urls));
+        assert_eq!("url".parse::<ExtraField>().unwrap_err(), r#"Unknown field "url", expected one of hashtags, mentions, urls, emojis"#);
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use crate::error::PrepError;
+
+/// A table of words built into the crate for some languages, or loaded from a file, e.g. the
+/// `Contractions`, the `Slang` or the `StopWords`.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::lexicon::Lexicon;
+/// use faster_tweet_nlp_toolkit::prep::stopwords::StopWords;
+/// let english = StopWords::from_language_or_file("en").unwrap();
+/// let merged = StopWords::from_names(&["en", "fr"]).unwrap();  // expect Some, the words of both lists
+/// ```
+pub trait Lexicon: Default {
+    /// The languages of the built-in tables.
+    const LANGUAGES: &'static [&'static str];
+
+    /// The built-in table of a language, None if there is none.
+    fn builtin(language: &str) -> Option<Arc<Self>>;
+
+    /// Load a table from a file.
+    fn from_file(path: &str) -> Result<Self, PrepError>;
+
+    /// Add the entries of `other`, which replace the known ones.
+    fn merge(&mut self, other: &Self);
+
+    /// Whether `name` is one of the `LANGUAGES` rather than a path.
+    fn is_language(name: &str) -> bool {
+        Self::LANGUAGES.contains(&name)
+    }
+
+    /// A built-in table, or else the table of a file, e.g. "en" or "custom.tsv".
+    fn from_language_or_file(name: &str) -> Result<Arc<Self>, PrepError> {
+        match Self::builtin(name) {
+            Some(table) => Ok(table),
+            None => Ok(Arc::new(Self::from_file(name)?)),
+        }
+    }
+
+    /// The tables of built-in languages or of files merged in order, None if there are no names.
+    fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Option<Arc<Self>>, PrepError> {
+        match names {
+            [] => Ok(None),
+            [name] => Ok(Some(Self::from_language_or_file(name.as_ref())?)),
+            names => {
+                let mut merged = Self::default();
+                for name in names {
+                    merged.merge(&*Self::from_language_or_file(name.as_ref())?);
+                }
+                Ok(Some(Arc::new(merged)))
+            }
+        }
+    }
+}
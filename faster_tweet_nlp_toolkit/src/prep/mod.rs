@@ -1,4 +1,14 @@
+pub mod alignment;
+pub mod compression;
+pub mod config;
+pub mod contractions;
+pub mod file_format;
+pub mod lexicon;
+pub mod options;
 pub mod regexes;
+pub mod segmenter;
+pub mod slang;
+pub mod stopwords;
 pub mod text_parser;
 pub mod text_prep;
 pub mod token;
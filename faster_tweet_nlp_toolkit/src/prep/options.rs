@@ -0,0 +1,443 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::PrepError;
+use crate::prep::config::{PrepConfig, PrepConfigBuilder, RetweetMode, SlangMode, Typography};
+use crate::prep::contractions::Contractions;
+use crate::prep::lexicon::Lexicon;
+use crate::prep::segmenter::Segmenter;
+use crate::prep::slang::Slang;
+use crate::prep::stopwords::StopWords;
+use crate::prep::token::ActionKind;
+use crate::prep::tokenizer::{RegexTokenizer, WhitespaceTokenizer};
+use crate::prep::utils::read_word_list;
+
+/// The names of the built-in presets, see [`PrepOptions::preset`].
+pub const PRESETS: [&str; 4] = ["bert-input", "topic-modeling", "sentiment", "word-embeddings"];
+
+/// The options of a `PrepConfig` as plain data, to save a pipeline to a file and load it back.
+///
+/// The files are TOML, JSON or YAML, according to their extension, and a missing option keeps its
+/// default value. The tokenizer is named rather than given as a `Tokenizer`, and the vocabulary, the
+/// contractions, the slang, the stop words and the unigrams of the segmenter are the paths of their
+/// files.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::options::{ConfigFormat, PrepOptions};
+/// use faster_tweet_nlp_toolkit::prep::text_prep::prep_with_config;
+/// let options = PrepOptions::parse("urls = \"remove\"\nto_lower = false\n", ConfigFormat::Toml).unwrap();
+/// let config = options.build().unwrap();
+/// let result = prep_with_config(String::from("Hello https://t.co/x"), &config).unwrap();  // expect "Hello"
+/// let sentiment = PrepOptions::preset("sentiment").unwrap().to_string(ConfigFormat::Json).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrepOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    pub remove_unencodable_char: bool,
+    #[serde(skip_serializing_if = "Typography::is_none")]
+    pub typography: Typography,
+    pub to_lower: bool,
+    pub strip_accents: bool,
+    pub reduce_len: bool,
+    pub max_repeat: usize,
+    pub protect_tokens: bool,
+    /// The path of a file of words, one per line, see `PrepConfig::vocabulary`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<String>,
+    pub retweet: RetweetMode,
+    /// The built-in languages, "en" or "fr", and the paths of files of contractions, merged in
+    /// this order, see `Contractions::from_file`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contractions: Vec<String>,
+    pub tokenizer: TokenizerOptions,
+    pub filters: Vec<String>,
+    /// The built-in language, "en", and the paths of files of slang, merged in this order, see
+    /// `Slang::from_file`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub slang: Vec<String>,
+    pub slang_mode: SlangMode,
+    /// The built-in languages, e.g. "en", and the paths of files of stop words, one per line, all
+    /// merged, see `StopWords::builtin`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stopwords: Vec<String>,
+    pub keep_negations: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emojis: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoticons: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hashtags: Option<ActionKind>,
+    /// The path of a file of `word count` lines, see `Segmenter::from_file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segmenter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cashtags: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urls: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digits: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub puncts: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emails: Option<ActionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html_tags: Option<ActionKind>,
+}
+
+/// A tokenizer of [`PrepOptions`], e.g. `tokenizer = "whitespace"` or `tokenizer = { regex = "\\S+" }`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerOptions {
+    /// `prep::tokenizer::TweetTokenizer`.
+    #[default]
+    Tweet,
+    /// `prep::tokenizer::WhitespaceTokenizer`.
+    Whitespace,
+    /// A `prep::tokenizer::RegexTokenizer` with this pattern.
+    Regex(String),
+}
+
+/// The format of a file of [`PrepOptions`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// The format telling by the extension of `path`, e.g. `pipeline.toml`.
+    pub fn from_extension(path: &str) -> Option<ConfigFormat> {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Some(ConfigFormat::Toml),
+            Some("json") => Some(ConfigFormat::Json),
+            Some("yaml" | "yml") => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PrepOptions {
+    fn default() -> PrepOptions {
+        let config = PrepConfig::default();
+        PrepOptions {
+            encoding: config.encoding,
+            remove_unencodable_char: config.remove_unencodable_char,
+            typography: config.typography,
+            to_lower: config.to_lower,
+            strip_accents: config.strip_accents,
+            reduce_len: config.reduce_len,
+            max_repeat: config.max_repeat,
+            protect_tokens: config.protect_tokens,
+            vocabulary: None,
+            retweet: config.retweet,
+            contractions: Vec::new(),
+            tokenizer: TokenizerOptions::Tweet,
+            filters: Vec::new(),
+            slang: Vec::new(),
+            slang_mode: config.slang_mode,
+            stopwords: Vec::new(),
+            keep_negations: config.keep_negations,
+            emojis: config.emojis,
+            emoticons: config.emoticons,
+            mentions: config.mentions,
+            hashtags: config.hashtags,
+            segmenter: None,
+            cashtags: config.cashtags,
+            urls: config.urls,
+            digits: config.digits,
+            puncts: config.puncts,
+            emails: config.emails,
+            html_tags: config.html_tags,
+        }
+    }
+}
+
+impl PrepOptions {
+    /// A built-in preset, one of [`PRESETS`]:
+    ///
+    /// * "bert-input": keeps the case and the punctuation for a transformer, tags the mentions,
+    ///   urls and emails, and demojizes the emojis.
+    /// * "topic-modeling": keeps the words only, the hashtags being segmented into words and the
+    ///   `RT @user: ` prefix of the retweets stripped.
+    /// * "sentiment": keeps the emoticons, emojis and punctuation, tags the mentions, urls and
+    ///   emails, and segments the hashtags.
+    /// * "word-embeddings": normalizes the typography, e.g. the curly quotes, so that the variants of
+    ///   a word are the same token, strips the `RT @user: ` prefix, and tags the mentions, urls,
+    ///   emails and digits.
+    pub fn preset(name: &str) -> Option<PrepOptions> {
+        let default = PrepOptions::default();
+        match name {
+            "bert-input" => Some(PrepOptions {
+                to_lower: false,
+                reduce_len: true,
+                emojis: Some(ActionKind::Demojize),
+                mentions: Some(ActionKind::Tag),
+                urls: Some(ActionKind::Tag),
+                emails: Some(ActionKind::Tag),
+                html_tags: Some(ActionKind::Remove),
+                ..default
+            }),
+            "topic-modeling" => Some(PrepOptions {
+                strip_accents: true,
+                reduce_len: true,
+                retweet: RetweetMode::Strip,
+                emojis: Some(ActionKind::Remove),
+                emoticons: Some(ActionKind::Remove),
+                mentions: Some(ActionKind::Remove),
+                hashtags: Some(ActionKind::Segment),
+                urls: Some(ActionKind::Remove),
+                digits: Some(ActionKind::Remove),
+                puncts: Some(ActionKind::Remove),
+                emails: Some(ActionKind::Remove),
+                html_tags: Some(ActionKind::Remove),
+                ..default
+            }),
+            "sentiment" => Some(PrepOptions {
+                reduce_len: true,
+                mentions: Some(ActionKind::Tag),
+                hashtags: Some(ActionKind::Segment),
+                urls: Some(ActionKind::Tag),
+                emails: Some(ActionKind::Tag),
+                html_tags: Some(ActionKind::Remove),
+                ..default
+            }),
+            "word-embeddings" => Some(PrepOptions {
+                typography: Typography::all(),
+                reduce_len: true,
+                retweet: RetweetMode::Strip,
+                mentions: Some(ActionKind::Tag),
+                urls: Some(ActionKind::Tag),
+                digits: Some(ActionKind::Tag),
+                emails: Some(ActionKind::Tag),
+                html_tags: Some(ActionKind::Remove),
+                ..default
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parse the options written in `format`, the path of the errors being "<string>".
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<PrepOptions, PrepError> {
+        PrepOptions::parse_file("<string>", contents, format)
+    }
+
+    /// Load the options of a TOML, JSON or YAML file.
+    ///
+    /// The paths of the vocabulary, of the contractions, of the slang, of the stop words and of the
+    /// segmenter are relative to the directory of the file.
+    pub fn from_file(path: &str) -> Result<PrepOptions, PrepError> {
+        let contents = fs::read_to_string(path).map_err(|err| PrepError::io(path, err))?;
+        let format = ConfigFormat::from_extension(path).ok_or_else(|| unknown_format(path))?;
+        let mut options = PrepOptions::parse_file(path, &contents, format)?;
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        let contractions = options.contractions.iter_mut().filter(|name| !Contractions::is_language(name));
+        let slang = options.slang.iter_mut().filter(|name| !Slang::is_language(name));
+        let stopwords = options.stopwords.iter_mut().filter(|name| !StopWords::is_language(name));
+        let lists = contractions.chain(slang).chain(stopwords);
+        for file in [&mut options.vocabulary, &mut options.segmenter].into_iter().flatten().chain(lists) {
+            *file = directory.join(&*file).to_string_lossy().into_owned();
+        }
+        Ok(options)
+    }
+
+    /// A preset, or else the options of a file, e.g. "sentiment" or "pipeline.toml".
+    pub fn from_preset_or_file(name: &str) -> Result<PrepOptions, PrepError> {
+        match PrepOptions::preset(name) {
+            Some(options) => Ok(options),
+            None if !Path::new(name).exists() => {
+                let message = format!("neither a preset, one of {}, nor a file", PRESETS.join(", "));
+                Err(PrepError::io(name, io::Error::new(io::ErrorKind::NotFound, message)))
+            }
+            None => PrepOptions::from_file(name),
+        }
+    }
+
+    fn parse_file(path: &str, contents: &str, format: ConfigFormat) -> Result<PrepOptions, PrepError> {
+        let error = |line: usize, message: String| PrepError::Parse { path: path.to_string(), line, message };
+        match format {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|err| {
+                let start = err.span().map(|span| span.start).unwrap_or_default();
+                error(contents[..start].matches('\n').count() + 1, err.message().to_string())
+            }),
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|err| error(err.line(), err.to_string())),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|err| {
+                error(err.location().map(|location| location.line()).unwrap_or(1), err.to_string())
+            }),
+        }
+    }
+
+    /// Write the options in `format`.
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String, PrepError> {
+        let error = |format, message: String| PrepError::Serialize { format, message };
+        match format {
+            ConfigFormat::Toml => toml::to_string(self).map_err(|err| error("TOML", err.to_string())),
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(|err| error("JSON", err.to_string())),
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(|err| error("YAML", err.to_string())),
+        }
+    }
+
+    /// Save the options to a TOML, JSON or YAML file, according to its extension.
+    pub fn save(&self, path: &str) -> Result<(), PrepError> {
+        let format = ConfigFormat::from_extension(path).ok_or_else(|| unknown_format(path))?;
+        fs::write(path, self.to_string(format)?).map_err(|err| PrepError::io(path, err))
+    }
+
+    /// Build the `PrepConfig`, reading the files of the vocabulary, of the contractions, of the slang,
+    /// of the stop words and of the segmenter.
+    pub fn build(&self) -> Result<PrepConfig, PrepError> {
+        let mut builder = PrepConfigBuilder::new();
+        if let Some(encoding) = &self.encoding {
+            builder.encoding(encoding);
+        }
+        builder
+            .remove_unencodable_char(self.remove_unencodable_char)
+            .typography(self.typography)
+            .to_lower(self.to_lower)
+            .strip_accents(self.strip_accents)
+            .reduce_len(self.reduce_len)
+            .max_repeat(self.max_repeat)
+            .protect_tokens(self.protect_tokens)
+            .retweet(self.retweet)
+            .filters(&self.filters)
+            .slang_mode(self.slang_mode)
+            .keep_negations(self.keep_negations);
+        if let Some(path) = &self.vocabulary {
+            builder.vocabulary(read_word_list(path)?);
+        }
+        if let Some(contractions) = Contractions::from_names(&self.contractions)? {
+            builder.contractions(contractions);
+        }
+        if let Some(slang) = Slang::from_names(&self.slang)? {
+            builder.slang(slang);
+        }
+        if let Some(stopwords) = StopWords::from_names(&self.stopwords)? {
+            builder.stopwords(stopwords);
+        }
+        match &self.tokenizer {
+            TokenizerOptions::Tweet => {}
+            TokenizerOptions::Whitespace => {
+                builder.tokenizer(WhitespaceTokenizer);
+            }
+            TokenizerOptions::Regex(pattern) => {
+                builder.tokenizer(RegexTokenizer::new(pattern)?);
+            }
+        }
+        if let Some(path) = &self.segmenter {
+            builder.segmenter(Segmenter::from_file(path)?);
+        }
+        let mut config = builder.build()?;
+        config.emojis = self.emojis;
+        config.emoticons = self.emoticons;
+        config.mentions = self.mentions;
+        config.hashtags = self.hashtags;
+        config.cashtags = self.cashtags;
+        config.urls = self.urls;
+        config.digits = self.digits;
+        config.puncts = self.puncts;
+        config.emails = self.emails;
+        config.html_tags = self.html_tags;
+        config.actions()?;
+        Ok(config)
+    }
+}
+
+fn unknown_format(path: &str) -> PrepError {
+    let message = "unknown config format, expected a .toml, .json, .yaml or .yml file";
+    PrepError::io(path, io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::prep::text_prep::prep_with_config;
+    use crate::test_dir::TestDir;
+
+    #[rstest]
+    #[case(ConfigFormat::Toml)]
+    #[case(ConfigFormat::Json)]
+    #[case(ConfigFormat::Yaml)]
+    fn test_options_round_trip(#[case] format: ConfigFormat) {
+        for name in PRESETS {
+            let options = PrepOptions::preset(name).unwrap();
+            assert_eq!(PrepOptions::parse(&options.to_string(format).unwrap(), format).unwrap(), options);
+        }
+        let options = PrepOptions {
+            encoding: Some("ascii".to_string()),
+            tokenizer: TokenizerOptions::Regex(r"\S+".to_string()),
+            filters: vec!["rt".to_string()],
+            retweet: RetweetMode::Tag,
+            typography: Typography { quotes: true, ..Typography::default() },
+            contractions: vec!["en".to_string(), "contractions.tsv".to_string()],
+            slang: vec!["en".to_string(), "slang.json".to_string()],
+            slang_mode: SlangMode::Annotate,
+            stopwords: vec!["en".to_string(), "fr".to_string()],
+            keep_negations: true,
+            ..PrepOptions::default()
+        };
+        assert_eq!(PrepOptions::parse(&options.to_string(format).unwrap(), format).unwrap(), options);
+    }
+
+    #[rstest]
+    #[case("bert-input", "RT @Bob: Sooooo HAPPY 😂 https://t.co/x #BlessedDay", "RT <MENTION> : Sooo HAPPY :joy: <URL> #BlessedDay")]
+    #[case("topic-modeling", "RT @Bob: Sooooo HAPPY 😂 https://t.co/x #BlessedDay", "sooo happy blessed day")]
+    #[case("sentiment", "RT @Bob: Sooooo HAPPY 😂 https://t.co/x #BlessedDay :)", "rt <MENTION> : sooo happy 😂 <URL> blessed day :)")]
+    #[case("word-embeddings", "RT @Bob: I’m Sooooo HAPPY… ２０２４ – https://t.co/x", "i'm sooo happy ... <DIGIT> - <URL>")]
+    fn test_presets(#[case] name: &str, #[case] text: &str, #[case] expected: &str) {
+        let config = PrepOptions::preset(name).unwrap().build().unwrap();
+        assert_eq!(prep_with_config(text.to_string(), &config).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("urls = \"remove\"\nurl = \"tag\"\n", ConfigFormat::Toml, "<string>:2: unknown field `url`")]
+    #[case("{\n\"urls\": \"delete\"}", ConfigFormat::Json, "<string>:2: unknown variant `delete`")]
+    #[case("max_repeat: many", ConfigFormat::Yaml, "<string>:1: max_repeat: invalid type")]
+    fn test_options_errors(#[case] contents: &str, #[case] format: ConfigFormat, #[case] expected: &str) {
+        let err = PrepOptions::parse(contents, format).unwrap_err();
+        assert!(err.to_string().starts_with(expected), "{}", err);
+    }
+
+    #[test]
+    fn test_options_build_error() {
+        let options = PrepOptions { mentions: Some(ActionKind::Emojize), ..PrepOptions::default() };
+        assert!(matches!(options.build(), Err(PrepError::Action(_))));
+    }
+
+    #[test]
+    fn test_options_from_file() {
+        let dir = TestDir::new("options_from_file");
+        fs::write(dir.path("words.txt"), "good\n").unwrap();
+        fs::write(dir.path("contractions.tsv"), "gonna\tgoing to\n").unwrap();
+        fs::write(dir.path("slang.json"), r#"{"smol": "small"}"#).unwrap();
+        fs::write(dir.path("stopwords.txt"), "via\n").unwrap();
+        let path = &dir.path("pipeline.yaml");
+        let options = PrepOptions {
+            reduce_len: true,
+            vocabulary: Some("words.txt".to_string()),
+            contractions: vec!["en".to_string(), "contractions.tsv".to_string()],
+            slang: vec!["en".to_string(), "slang.json".to_string()],
+            stopwords: vec!["en".to_string(), "stopwords.txt".to_string()],
+            keep_negations: true,
+            ..PrepOptions::default()
+        };
+        options.save(path).unwrap();
+        let config = PrepOptions::from_preset_or_file(path).unwrap().build().unwrap();
+        assert_eq!(prep_with_config("goooood".to_string(), &config).unwrap(), "good");
+        assert_eq!(prep_with_config("Gonna? Can't".to_string(), &config).unwrap(), "going ? not");
+        assert_eq!(prep_with_config("smol thx via the web".to_string(), &config).unwrap(), "small thanks web");
+        assert!(PrepOptions::from_file(&dir.path("words.txt")).is_err());
+        let err = PrepOptions::from_preset_or_file("sentimental").unwrap_err();
+        assert_eq!(err.to_string(), "sentimental: neither a preset, one of bert-input, topic-modeling, sentiment, word-embeddings, nor a file");
+    }
+}
@@ -16,7 +16,7 @@ fn string_to_static_str(s: String) -> &'static str {
     Box::leak(s.into_boxed_str())
 }
 
-static REGEX_BUILDER: Lazy<Mutex<RegexBuilder>> = Lazy::new(|| {
+pub(crate) static REGEX_BUILDER: Lazy<Mutex<RegexBuilder>> = Lazy::new(|| {
     let mut builder: RegexBuilder = RegexBuilder::new();
     builder.ucp(true);
     Mutex::new(builder)
@@ -30,6 +30,10 @@ lazy_static! {
     pub static ref NOT_A_HASHTAG: &'static str = r#"\#\b[p{N}]+\b"#;
     pub static ref NOT_A_HASHTAG_RE: Regex = REGEX_BUILDER.lock().unwrap().build(r#"^\#\b[\p{N}]+\b$"#).unwrap();
 
+    // a $ followed by a ticker of 1 to 6 letters, with an optional class suffix like $BRK.A
+    pub static ref CASHTAG: &'static str = r#"(?<![\w$])\$[A-Za-z]{1,6}(?:[._][A-Za-z]{1,2})?(?![\w$])"#;
+    pub static ref CASHTAG_RE: Regex = REGEX_BUILDER.lock().unwrap().build(r#"^\$[A-Za-z]{1,6}(?:[._][A-Za-z]{1,2})?$"#).unwrap();
+
     pub static ref WORD: &'static str = r#"(?:[^\W\d|(?:_](?:[^\W\d_]|['\-_]|[\x{0E00}-\x{0E7F}])+[^\W\d_]?)[^\W\d]?"#;
 
     pub static ref MENTION:&'static str = r#"@\w+"#;
@@ -59,7 +63,7 @@ lazy_static! {
     ];
     static ref _LTR_FACE: &'static str = string_to_static_str(_LTR_EMOTICON.map(|x| x.to_string()).join(""));
     static ref _RTL_FACE: &'static str = string_to_static_str(_RTL_EMOTICON.map(|x| x.to_string()).join(""));
-    static ref _EASTERN_EMOTICONS: &'static str = r#"(?<![\w])(?:(?:[<>]?[\^;][\W_m][\;^][;<>]?)|(?:[^\s()]?m?[\(][\W_oTOJ]{1,3}[\s]?[\W_oTOJ]{1,3}[)]m?[^\s()]?)|(?:\*?[v>\-\/\\][o0O\_\.][v\-<\/\\]\*?)|(?:[oO0>][\-_\/oO\.\\]{1,2}[oO0>])|(?:\^\^))(?![\w])"#;
+    static ref _EASTERN_EMOTICONS: &'static str = r#"(?<![\w])(?:(?:[<>]?[\^;][\W_m][\;^][;<>]?)|(?:[^\s()]?m?[\(][\W\p{Pc}oTOJ]{1,3}[\s]?[\W\p{Pc}oTOJ]{1,3}[)]m?[^\s()]?)|(?:\*?[v>\-\/\\][o0O\_\.][v\-<\/\\]\*?)|(?:[oO0>][\-_\/oO\.\\]{1,2}[oO0>])|(?:\^\^))(?![\w])"#;
     static ref _REST_EMOTICONS: &'static str = r#"(?<![A-Za-z0-9/()])(?:(?:\^5)|(?:\<3))(?![[A-Za-z0-9/()])"#;
     static ref _EMOTICONS: [&'static str; 4] = [
         &_LTR_FACE,
@@ -77,7 +81,11 @@ lazy_static! {
     pub static ref URL: &'static str = r#"(?:https?://[^\s\.]+\.[^\s]{2,}|www\.[^\s]+\.[^\s]{2,})"#;
     pub static ref URL_RE: Regex = REGEX_BUILDER.lock().unwrap().build(r#"^(?:https?://[^\s\.]+\.[^\s]{2,}|www\.[^\s]+\.[^\s]{2,})$"#).unwrap();
 
-    pub static ref CAMEL_SPLIT: &'static str = r#"((?<=[a-z])[A-Z]|(?<!^)[A-Z](?=[a-z])|[0-9]+|(?<=[0-9\\-\\_])[A-Za-z]|[\\-\\_])"#;
+    // the tokens left untouched by the lengthening reduction
+    pub static ref PROTECTED_TOKENS_RE: Regex = REGEX_BUILDER.lock().unwrap().build(&[*URL, *EMAIL, *MENTION, *DIGIT].join("|")).unwrap();
+
+    pub static ref CAMEL_SPLIT: &'static str = r#"((?<=[a-z])[A-Z]|(?<!^)[A-Z](?=[a-z])|[0-9]+|(?<=[0-9\-_])[A-Za-z]|[\-_])"#;
+    pub static ref CAMEL_SPLIT_RE: Regex = REGEX_BUILDER.lock().unwrap().build(&CAMEL_SPLIT).unwrap();
 
     pub static ref HTML_TAG: &'static str = r#"<[^>\s]+>"#;
     pub static ref HTML_TAG_RE: Regex = REGEX_BUILDER.lock().unwrap().build(r#"^<[^>\s]+>$"#).unwrap();
@@ -94,13 +102,31 @@ lazy_static! {
     pub static ref QUOTES_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(r#"[“”«»]"#).unwrap();
     pub static ref APOSTROPHES_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(r#"[‘’]"#).unwrap();
     pub static ref URL_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(&URL).unwrap();
-    pub static ref RT_MENTION_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(&(r#"^RT "#.to_string() + &MENTION + &r#": "#.to_string())).unwrap();
-
-    // join all together
-    static ref _TOKEN_PIPELINE: [&'static str; 12] = [
-        &URL, &EMAIL, &MENTION, &HASHTAG, &EMOTICONS, &HTML_TAG, &ASCII_ARROW, &DIGIT, &ELLIPSIS_DOTS, &EMOJI_STRING, &WORD, r#"\S"#
+    // a word and its apostrophes, neither in a hashtag, a mention or a cashtag nor followed by an apostrophe
+    pub static ref CONTRACTION_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(r#"(?<![\w'’#@$])\w++(?:['’]\w++)*+(?![\w'’])"#).unwrap();
+    pub static ref RT_MENTION_PAT: Regex = REGEX_BUILDER.lock().unwrap().build(&(r#"^RT "#.to_string() + &MENTION + r#": "#)).unwrap();
+
+    pub static ref WORD_RE: Regex = REGEX_BUILDER.lock().unwrap().build(&format!(r#"^(?:{})$"#, *WORD)).unwrap();
+
+    // join all together, each pattern in a named group telling which one matched
+    pub(crate) static ref _TOKEN_PIPELINE: [(&'static str, &'static str); 13] = [
+        ("url", &URL),
+        ("email", &EMAIL),
+        ("mention", &MENTION),
+        ("hashtag", &HASHTAG),
+        // before the emoticons, whose eyes can be a $
+        ("cashtag", &CASHTAG),
+        ("emoticon", &EMOTICONS),
+        ("html_tag", &HTML_TAG),
+        ("ascii_arrow", &ASCII_ARROW),
+        ("digit", &DIGIT),
+        ("ellipsis_dots", &ELLIPSIS_DOTS),
+        ("emoji_string", &EMOJI_STRING),
+        ("word", &WORD),
+        ("other", r#"\S"#),
     ];
-    static ref TOKEN_PIPELINE: &'static str = string_to_static_str(_TOKEN_PIPELINE.map(|x| x.to_string()).join(r"|"));
+    static ref TOKEN_PIPELINE: &'static str = string_to_static_str(
+        _TOKEN_PIPELINE.map(|(name, pattern)| format!(r#"(?<{}>{})"#, name, pattern)).join(r"|"));
     pub static ref TWEET_TOKENIZE: Regex = REGEX_BUILDER.lock().unwrap().build(&TOKEN_PIPELINE).unwrap();
 }
 
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::error::PrepError;
+use crate::prep::regexes::CAMEL_SPLIT_RE;
+
+lazy_static! {
+    /// The segmenter built from the English unigrams shipped with the crate, whose counts are
+    /// estimated from the rank of the words, see `data/README.md`.
+    pub static ref DEFAULT_SEGMENTER: Arc<Segmenter> = Arc::new(
+        Segmenter::parse("en_unigrams.txt", include_str!("../../data/en_unigrams.txt")).unwrap());
+}
+
+/// Split hashtags into words.
+///
+/// The camel case and the digits of a hashtag tell where its words are, e.g. `#DavidLynchFan`.
+/// The lowercase runs left, e.g. `#climatechange`, are split into the most likely sequence of
+/// words according to the frequencies of a unigram table (Viterbi algorithm).
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::segmenter::Segmenter;
+/// let segmenter = Segmenter::new([("new", 10), ("york", 5), ("newyork", 1)]);
+/// let words = segmenter.segment("newyorkcity");  // expect ["new", "york", "city"]
+/// let hashtag = segmenter.segment_hashtag("#ILoveNewYork", None);  // expect "I Love New York"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Segmenter {
+    counts: HashMap<String, u64>,
+    total: f64,
+    /// The length in chars of the longest known word.
+    max_len: usize,
+}
+
+impl Segmenter {
+    /// Create a segmenter from the count of each word, the words being lowercased.
+    pub fn new<I, S>(counts: I) -> Segmenter
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        let mut segmenter = Segmenter::default();
+        for (word, count) in counts {
+            *segmenter.counts.entry(word.as_ref().to_lowercase()).or_default() += count;
+        }
+        segmenter.total = segmenter.counts.values().sum::<u64>() as f64;
+        segmenter.max_len = segmenter.counts.keys().map(|word| word.chars().count()).max().unwrap_or_default();
+        segmenter
+    }
+
+    /// Load a unigram table with one word and its count per line, separated by whitespace.
+    pub fn from_file(path: &str) -> Result<Segmenter, PrepError> {
+        let contents = fs::read_to_string(path).map_err(|err| PrepError::io(path, err))?;
+        Segmenter::parse(path, &contents)
+    }
+
+    fn parse(path: &str, contents: &str) -> Result<Segmenter, PrepError> {
+        let mut counts = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let error = |message: String| PrepError::Parse { path: path.to_string(), line: i + 1, message };
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [] => continue,
+                [word, count] => {
+                    let count = count.parse().map_err(|_| error(format!(r#"invalid count "{}""#, count)))?;
+                    counts.push((word, count));
+                }
+                _ => return Err(error("expected a word and its count".to_string())),
+            }
+        }
+        Ok(Segmenter::new(counts))
+    }
+
+    /// The log probability of `word`, made of `len` chars.
+    ///
+    /// An unknown word gets a probability decreasing with its length, so that a run of unknown
+    /// chars stays in one piece rather than being split into single letters.
+    fn log_probability(&self, word: &str, len: usize) -> f64 {
+        match self.counts.get(word).filter(|_| len <= self.max_len) {
+            Some(&count) => (count as f64 / self.total).ln(),
+            None => (10.0 / self.total.max(1.0)).ln() - len as f64 * 10f64.ln(),
+        }
+    }
+
+    /// Split a lowercase text without spaces into its most likely sequence of words.
+    ///
+    /// Only the last `max_len` chars can make a known word, a longer run of unknown chars being
+    /// grown one char at a time from the unknown word ending just before.
+    pub fn segment(&self, text: &str) -> Vec<String> {
+        let bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).chain(std::iter::once(text.len())).collect();
+        let n = bounds.len() - 1;
+        // the best score of the first `end` chars, and where its last word starts
+        let mut best = vec![(f64::NEG_INFINITY, 0); n + 1];
+        best[0].0 = 0.0;
+        for end in 1..=n {
+            let window = end.saturating_sub(self.max_len.max(1));
+            let previous = best[end - 1].1;
+            let unknown_run = (previous < window && !self.counts.contains_key(&text[bounds[previous]..bounds[end - 1]]))
+                .then_some(previous);
+            for start in unknown_run.into_iter().chain(window..end) {
+                let score = best[start].0 + self.log_probability(&text[bounds[start]..bounds[end]], end - start);
+                if score > best[end].0 {
+                    best[end] = (score, start);
+                }
+            }
+        }
+        let mut words = Vec::new();
+        let mut end = n;
+        while end > 0 {
+            let start = best[end].1;
+            words.push(text[bounds[start]..bounds[end]].to_string());
+            end = start;
+        }
+        words.reverse();
+        words
+    }
+
+    /// Split a hashtag into words joined by spaces, e.g. `#DavidLynchFan` into `David Lynch Fan`.
+    ///
+    /// `raw` is the hashtag before the preprocessing: if `value` is its lowercase version, the
+    /// camel case of `raw` is used, and the result is lowercased.
+    pub fn segment_hashtag(&self, value: &str, raw: Option<&str>) -> String {
+        let (hashtag, lowercase) = match raw {
+            Some(raw) if raw != value && raw.to_lowercase() == value => (raw, true),
+            _ => (value, false),
+        };
+        let hashtag = hashtag.strip_prefix('#').unwrap_or(hashtag);
+        let mut words = Vec::new();
+        for piece in split_camel_case(hashtag) {
+            if piece.chars().all(char::is_lowercase) {
+                words.extend(self.segment(piece));
+            } else {
+                words.push(piece.to_string());
+            }
+        }
+        let segmented = words.join(" ");
+        if lowercase {
+            segmented.to_lowercase()
+        } else {
+            segmented
+        }
+    }
+}
+
+/// Split a text before the uppercase letters starting a word, around the digits, and on the
+/// dashes and underscores.
+fn split_camel_case(text: &str) -> Vec<&str> {
+    // a hashtag is too short to exhaust the match limit, a failed match just splits nothing
+    let starts = CAMEL_SPLIT_RE.find_iter(text.as_bytes())
+        .filter_map(Result::ok)
+        .map(|m| m.start());
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for start in starts.chain(std::iter::once(text.len())) {
+        pieces.extend(text[last..start].split(['-', '_']).filter(|piece| !piece.is_empty()));
+        last = start;
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[rstest]
+    #[case("DavidLynchFan", vec!["David", "Lynch", "Fan"])]
+    #[case("NewYear2019Party", vec!["New", "Year", "2019", "Party"])]
+    #[case("covid_19", vec!["covid", "19"])]
+    #[case("iPhone", vec!["i", "Phone"])]
+    #[case("mother-in-law", vec!["mother", "in", "law"])]
+    fn test_split_camel_case(#[case] text: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(split_camel_case(text), expected)
+    }
+
+    #[rstest]
+    #[case("climatechange", vec!["climate", "change"])]
+    #[case("happynewyear", vec!["happy", "new", "year"])]
+    #[case("stateoftheart", vec!["state", "of", "the", "art"])]
+    #[case("xqzvk", vec!["xqzvk"])]
+    #[case("", vec![])]
+    fn test_segment(#[case] text: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(DEFAULT_SEGMENTER.segment(text), expected)
+    }
+
+    #[rstest]
+    #[case("newyork", vec!["new", "york"])]
+    #[case("newzzzzzzzzyork", vec!["new", "zzzzzzzz", "york"])]
+    #[case("zzzzzzzzzz", vec!["zzzzzzzzzz"])]
+    fn test_segment_unknown_run(#[case] text: &str, #[case] expected: Vec<&str>) {
+        // the unknown runs are longer than the longest known word
+        let segmenter = Segmenter::new([("new", 10), ("york", 5)]);
+        assert_eq!(segmenter.segment(text), expected)
+    }
+
+    #[rstest]
+    #[case("#DavidLynchFan", None, "David Lynch Fan")]
+    #[case("#davidlynchfan", Some("#DavidLynchFan"), "david lynch fan")]
+    #[case("#climatechange", Some("#climatechange"), "climate change")]
+    #[case("#lovewins", Some("#LoveWins!"), "love wins")]
+    fn test_segment_hashtag(#[case] value: &str, #[case] raw: Option<&str>, #[case] expected: &str) {
+        assert_eq!(DEFAULT_SEGMENTER.segment_hashtag(value, raw), expected)
+    }
+
+    #[test]
+    fn test_segmenter_from_file() {
+        let dir = TestDir::new("segmenter_from_file");
+        let path = dir.path("unigrams.txt");
+        fs::write(&path, "Rust 10\nacean 2\n\nrustacean 1\n").unwrap();
+        let segmenter = Segmenter::from_file(&path).unwrap();
+        assert_eq!(segmenter.segment("rustacean"), vec!["rust", "acean"]);
+
+        fs::write(&path, "rust 10\nacean two\n").unwrap();
+        let err = Segmenter::from_file(&path).unwrap_err();
+        assert!(err.to_string().ends_with(r#"unigrams.txt:2: invalid count "two""#));
+    }
+}
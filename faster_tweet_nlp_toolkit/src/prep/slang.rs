@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::error::PrepError;
+use crate::prep::config::SlangMode;
+use crate::prep::lexicon::Lexicon;
+use crate::prep::token::{Token, TokenKind};
+use crate::prep::utils::{match_case, parse_expansions};
+
+lazy_static! {
+    /// The English slang and abbreviations shipped with the crate, e.g. "idk" to "i do not know".
+    pub static ref ENGLISH_SLANG: Arc<Slang> = Arc::new(
+        Slang::parse("en_slang.tsv", include_str!("../../data/en_slang.tsv")).unwrap());
+}
+
+/// Normalize the slang and the abbreviations of the tokens, e.g. "u" to "you" or "idk" to "i do not know".
+///
+/// A token is looked up whatever its case, the expansion taking the case of the token, e.g. "IDK"
+/// to "I DO NOT KNOW". Only the words and the other tokens are normalized, never the urls, emails,
+/// mentions, hashtags, cashtags, digits, emojis, emoticons or HTML tags.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::SlangMode;
+/// use faster_tweet_nlp_toolkit::prep::slang::{Slang, ENGLISH_SLANG};
+/// use faster_tweet_nlp_toolkit::prep::token::Token;
+/// let expansion = ENGLISH_SLANG.expand("Brb");  // expect Some("Be right back")
+/// let slang = Slang::new([("smol", "small")]);
+/// let mut token = Token::new("smol".to_string());
+/// slang.normalize(&mut token, SlangMode::Append).unwrap();  // expect the value "smol small"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Slang {
+    /// The expansion of each lowercase word.
+    words: HashMap<String, String>,
+}
+
+impl Slang {
+    /// Create the slang from `(word, expansion)` pairs.
+    pub fn new<I, S, T>(mappings: I) -> Slang
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: Into<String>,
+    {
+        let mut slang = Slang::default();
+        slang.extend(mappings);
+        slang
+    }
+
+    /// Add `(word, expansion)` pairs, replacing the expansion of a known word.
+    pub fn extend<I, S, T>(&mut self, mappings: I)
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: Into<String>,
+    {
+        self.words.extend(mappings.into_iter().map(|(word, expansion)| (word.as_ref().to_lowercase(), expansion.into())));
+    }
+
+    fn parse(path: &str, contents: &str) -> Result<Slang, PrepError> {
+        Ok(Slang::new(parse_expansions(path, contents)?))
+    }
+
+    /// The expansion of a word, in the case of the word, or None if it is not known.
+    pub fn expand(&self, word: &str) -> Option<String> {
+        self.words.get(&word.to_lowercase()).map(|expansion| match_case(word, expansion))
+    }
+
+    /// Normalize a token according to `mode`, return whether it was known.
+    pub fn normalize(&self, token: &mut Token, mode: SlangMode) -> Result<bool, PrepError> {
+        if !matches!(token.kind, TokenKind::Word | TokenKind::Other) {
+            return Ok(false)
+        }
+        let Some(expansion) = self.expand(&token.value) else {
+            return Ok(false)
+        };
+        match mode {
+            SlangMode::Replace => token.try_set_value(expansion.clone())?,
+            SlangMode::Append => token.try_set_value(format!("{} {}", token.value, expansion))?,
+            SlangMode::Annotate => {}
+        }
+        token.expansion = Some(expansion);
+        Ok(true)
+    }
+}
+
+impl Lexicon for Slang {
+    const LANGUAGES: &'static [&'static str] = &["en"];
+
+    /// The built-in list of a language, "en".
+    fn builtin(language: &str) -> Option<Arc<Slang>> {
+        match language {
+            "en" => Some(ENGLISH_SLANG.clone()),
+            _ => None,
+        }
+    }
+
+    /// Load the slang of a file with one word and its expansion per line, separated by a tab, or of
+    /// a `.json` file of an object of expansions.
+    fn from_file(path: &str) -> Result<Slang, PrepError> {
+        let contents = fs::read_to_string(path).map_err(|err| PrepError::io(path, err))?;
+        Slang::parse(path, &contents)
+    }
+
+    /// Add the words of `other`, which replace the known ones.
+    fn merge(&mut self, other: &Slang) {
+        self.extend(other.words.iter().map(|(word, expansion)| (word, expansion.clone())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("idk", SlangMode::Replace, "i do not know")]
+    #[case("IDK", SlangMode::Replace, "I DO NOT KNOW")]
+    #[case("u", SlangMode::Append, "u you")]
+    #[case("Lol", SlangMode::Annotate, "Lol")]
+    fn test_normalize(#[case] value: &str, #[case] mode: SlangMode, #[case] expected: &str) {
+        let mut token = Token::new(value.to_string());
+        assert!(ENGLISH_SLANG.normalize(&mut token, mode).unwrap());
+        assert_eq!(token.value, expected);
+        assert_eq!(token.expansion, ENGLISH_SLANG.expand(value));
+    }
+
+    #[rstest]
+    #[case("@lol")]
+    #[case("#idk")]
+    #[case("u@idk.com")]
+    #[case("www.lol.com")]
+    #[case("hello")]
+    fn test_normalize_unknown_or_protected(#[case] value: &str) {
+        let mut token = Token::new(value.to_string());
+        assert!(!ENGLISH_SLANG.normalize(&mut token, SlangMode::Replace).unwrap());
+        assert_eq!(token.value, value);
+        assert_eq!(token.expansion, None);
+    }
+
+    #[test]
+    fn test_extend_and_merge() {
+        let mut slang = Slang::new([("smol", "small"), ("u", "you all")]);
+        slang.merge(&ENGLISH_SLANG);
+        slang.extend([("LOL", "haha")]);
+        assert_eq!(slang.expand("smol").as_deref(), Some("small"));
+        assert_eq!(slang.expand("u").as_deref(), Some("you"));
+        assert_eq!(slang.expand("lol").as_deref(), Some("haha"));
+    }
+}
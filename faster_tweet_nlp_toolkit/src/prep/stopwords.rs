@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::error::PrepError;
+use crate::prep::lexicon::Lexicon;
+use crate::prep::token::{Token, TokenKind};
+use crate::prep::utils::read_word_list;
+
+lazy_static! {
+    static ref ENGLISH_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/en.txt").lines()));
+    static ref FRENCH_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/fr.txt").lines()));
+    static ref SPANISH_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/es.txt").lines()));
+    static ref GERMAN_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/de.txt").lines()));
+    static ref ITALIAN_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/it.txt").lines()));
+    static ref PORTUGUESE_STOPWORDS: Arc<StopWords> = Arc::new(StopWords::new(include_str!("../../data/stopwords/pt.txt").lines()));
+}
+
+/// The negations of the built-in languages, which `keep_negations` leaves in the text as they
+/// carry the polarity of a sentence, e.g. "not" in "not good".
+pub const NEGATIONS: [&str; 66] = [
+    // en
+    "no", "not", "nor", "never", "none", "nobody", "nothing", "nowhere", "neither", "cannot",
+    "ain't", "aren't", "can't", "couldn't", "didn't", "doesn't", "don't", "hadn't", "hasn't",
+    "haven't", "isn't", "mustn't", "shan't", "shouldn't", "wasn't", "weren't", "won't", "wouldn't",
+    // fr
+    "ne", "pas", "ni", "jamais", "rien", "aucun", "aucune",
+    // es
+    "nunca", "nada", "nadie", "ningún", "ninguna", "ninguno", "tampoco",
+    // de
+    "nicht", "nichts", "nie", "niemals", "niemand", "kein", "keine", "keinem", "keinen", "keiner",
+    "keines", "weder",
+    // it
+    "non", "mai", "né", "nessuno", "nessuna", "niente", "nulla",
+    // pt
+    "não", "nem", "ninguém", "nenhum", "nenhuma",
+];
+
+/// Whether a lowercase word is a negation, see [`NEGATIONS`].
+pub fn is_negation(word: &str) -> bool {
+    NEGATIONS.contains(&word)
+}
+
+/// A list of stop words, the frequent words carrying little meaning like "the" or "of".
+///
+/// The words are matched whatever their case, and only the words and the other tokens can be stop
+/// words, never the urls, emails, mentions, hashtags, digits or emojis.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::lexicon::Lexicon;
+/// use faster_tweet_nlp_toolkit::prep::stopwords::StopWords;
+/// let stopwords = StopWords::builtin("en").unwrap();
+/// let is_stop_word = stopwords.contains("The");  // expect true
+/// let mut custom = StopWords::new(["rt", "via"]);
+/// custom.merge(&stopwords);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StopWords {
+    /// The lowercase stop words.
+    words: HashSet<String>,
+}
+
+impl StopWords {
+    /// Create a list of stop words, the blank ones being skipped.
+    pub fn new<I, S>(words: I) -> StopWords
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut stopwords = StopWords::default();
+        stopwords.extend(words);
+        stopwords
+    }
+
+    /// Add stop words.
+    pub fn extend<I, S>(&mut self, words: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.words.extend(words.into_iter()
+            .map(|word| word.as_ref().trim().to_lowercase())
+            .filter(|word| !word.is_empty()));
+    }
+
+    /// Whether a word is a stop word, whatever its case.
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+
+    /// Whether a token is a stop word, the negations being kept out of the list if `keep_negations`.
+    pub fn matches(&self, token: &Token, keep_negations: bool) -> bool {
+        if !matches!(token.kind, TokenKind::Word | TokenKind::Other) {
+            return false
+        }
+        let word = token.value.to_lowercase();
+        self.words.contains(&word) && !(keep_negations && is_negation(&word))
+    }
+}
+
+impl Lexicon for StopWords {
+    const LANGUAGES: &'static [&'static str] = &["en", "fr", "es", "de", "it", "pt"];
+
+    /// The built-in list of a language: "en", "fr", "es", "de", "it" or "pt".
+    fn builtin(language: &str) -> Option<Arc<StopWords>> {
+        match language {
+            "en" => Some(ENGLISH_STOPWORDS.clone()),
+            "fr" => Some(FRENCH_STOPWORDS.clone()),
+            "es" => Some(SPANISH_STOPWORDS.clone()),
+            "de" => Some(GERMAN_STOPWORDS.clone()),
+            "it" => Some(ITALIAN_STOPWORDS.clone()),
+            "pt" => Some(PORTUGUESE_STOPWORDS.clone()),
+            _ => None,
+        }
+    }
+
+    /// Load the stop words of a file, one per line.
+    fn from_file(path: &str) -> Result<StopWords, PrepError> {
+        Ok(StopWords::new(read_word_list(path)?))
+    }
+
+    /// Add the stop words of `other`.
+    fn merge(&mut self, other: &StopWords) {
+        self.words.extend(other.words.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[rstest]
+    #[case("en", "The", false, true)]
+    #[case("en", "not", false, true)]
+    #[case("en", "not", true, false)]
+    #[case("en", "Don't", true, false)]
+    #[case("en", "happy", false, false)]
+    #[case("en", "#the", false, false)]
+    #[case("en", "@you", false, false)]
+    #[case("fr", "pas", true, false)]
+    #[case("de", "Keine", true, false)]
+    #[case("pt", "não", true, false)]
+    #[case("pt", "não", false, true)]
+    fn test_matches(#[case] language: &str, #[case] value: &str, #[case] keep_negations: bool, #[case] expected: bool) {
+        let stopwords = StopWords::builtin(language).unwrap();
+        assert_eq!(stopwords.matches(&Token::new(value.to_string()), keep_negations), expected);
+    }
+
+    #[test]
+    fn test_builtin_negations() {
+        // a negation missing from every list is a typo
+        for negation in NEGATIONS {
+            let known = StopWords::LANGUAGES.iter().any(|language| StopWords::builtin(language).unwrap().contains(negation));
+            assert!(known, "{}", negation);
+        }
+    }
+
+    #[test]
+    fn test_from_file() {
+        let dir = TestDir::new("stopwords_from_file");
+        let path = dir.path("stopwords.txt");
+        std::fs::write(&path, "RT\n via \n\n").unwrap();
+        let stopwords = StopWords::from_language_or_file(&path).unwrap();
+        assert_eq!(*stopwords, StopWords::new(["rt", "via"]));
+        assert!(StopWords::from_language_or_file("xx").is_err());
+    }
+}
@@ -1,25 +1,50 @@
 #![allow(dead_code, unused)]
-use std::{ops::{Index, IndexMut}, collections::HashSet, borrow::Borrow, char::REPLACEMENT_CHARACTER};
+use std::{ops::{Index, IndexMut}, collections::HashSet, sync::Arc, borrow::Borrow, char::REPLACEMENT_CHARACTER};
 use itertools::Itertools;
 use lazy_static::{__Deref, lazy_static};
 use pcre2::bytes::{Regex, Match};
 use encoding_rs::{self, REPLACEMENT};
+use serde::{Deserialize, Serialize};
 
-use crate::prep::token::{Token, Action};
-use crate::prep::utils::{strip_accents_unicode, remove_variation_selectors, preprocess_url};
-use crate::prep::tokenizer::tweet_tokenize;
+use crate::constants::{RT_TAG, VARIATION_SELECTORS};
+use crate::error::PrepError;
+use crate::prep::alignment::{AlignedText, Alignment};
+#[allow(deprecated)]
+use crate::prep::config::parse_action_name;
+use crate::prep::config::{PrepConfig, RetweetMode, SlangMode, Typography};
+use crate::prep::token::{Token, Action, ActionKind};
+use crate::prep::contractions::Contractions;
+use crate::prep::regexes::{APOSTROPHES_PAT, CONTRACTION_PAT, PROTECTED_TOKENS_RE, QUOTES_PAT, RT_MENTION_PAT};
+use crate::prep::utils::HTTP_RE;
+use unicode_categories::UnicodeCategories;
+use crate::prep::tokenizer::{Tokenizer, TweetTokenizer};
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedText {
     pub tokens: Vec<Token>,
     pub split: String,
     pub value: Option<String>,
+    /// The alignment of the preprocessed text with the raw text, None if the tokens weren't parsed from a text.
+    pub alignment: Option<Alignment>,
 }
 
 impl ParsedText{
     pub fn len(&self) -> usize {
         self.tokens.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Apply the token actions named by the positional arguments, see `process_with_config`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on an unknown action name or a matching error, use `process_with_config` to get them
+    /// as a `PrepError` instead.
+    #[deprecated(note = "use `ParsedText::process_with_config` instead")]
+    #[allow(deprecated, clippy::too_many_arguments)]
     pub fn process(
         &mut self,
         mentions_action: Option<&str>,
@@ -31,87 +56,111 @@ impl ParsedText{
         puncts_action: Option<&str>,
         emails_action: Option<&str>,
         html_tags_action: Option<&str>,
-    ) -> () {
+    ) {
+        let config = PrepConfig {
+            mentions: parse_action_name(mentions_action),
+            hashtags: parse_action_name(hashtags_action),
+            urls: parse_action_name(urls_action),
+            digits: parse_action_name(digits_action),
+            emojis: parse_action_name(emojis_action),
+            emoticons: parse_action_name(emoticons_actions),
+            puncts: parse_action_name(puncts_action),
+            emails: parse_action_name(emails_action),
+            html_tags: parse_action_name(html_tags_action),
+            ..PrepConfig::default()
+        };
+        self.process_with_config(&config).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Flag the stop words of `config` and apply its token actions to every token, then drop the
+    /// emptied tokens.
+    pub fn process_with_config(&mut self, config: &PrepConfig) -> Result<(), PrepError> {
+        let actions = config.actions()?;
         for token in &mut self.tokens {
-            for action in [
-                &Action{action_name: mentions_action.map(|s| s.to_string()), action_condition: "is_mention".to_owned()},
-                &Action{action_name: hashtags_action.map(|s| s.to_string()), action_condition: "is_hashtag".to_owned()},
-                &Action{action_name: urls_action.map(|s| s.to_string()), action_condition: "is_url".to_owned()},
-                &Action{action_name: digits_action.map(|s| s.to_string()), action_condition: "is_digit".to_owned()},
-                &Action{action_name: emojis_action.map(|s| s.to_string()), action_condition: "is_emoji".to_owned()},
-                &Action{action_name: emoticons_actions.map(|s| s.to_string()), action_condition: "is_emoticon".to_owned()},
-                &Action{action_name: puncts_action.map(|s| s.to_string()), action_condition: "is_punct".to_owned()},
-                &Action{action_name: emails_action.map(|s| s.to_string()), action_condition: "is_email".to_owned()},
-                &Action{action_name: html_tags_action.map(|s| s.to_string()), action_condition: "is_html_tag".to_owned()},
-            ] {
-                if token.do_action(action) {
+            if let Some(stopwords) = &config.stopwords {
+                token.stop_word = stopwords.matches(token, config.keep_negations);
+            }
+            let raw = self.alignment.as_ref().zip(token.span).map(|(alignment, span)| &alignment.raw()[span.start..span.end]);
+            for action in &actions {
+                if action.try_apply_with_raw(token, raw)? {
                     break;
                 }
             }
         }
-        self.tokens = self.tokens
-        .iter()
-        .filter(|token| token.value.len() > 0)
-        .map(|x| Token{ value: (*x.value).to_string()})
-        .collect::<Vec<_>>()
+        self.tokens.retain(|token| !token.value.is_empty());
+        self.value = None;
+        Ok(())
     }
 
-    pub fn post_process(&mut self) -> () {
+    pub fn post_process(&mut self) -> Result<(), PrepError> {
         let text = self.value();
         lazy_static! {
             static ref CONTINUOUS_SPACES: Regex = Regex::new(r"\s+").unwrap();
         }
         let result = CONTINUOUS_SPACES.replace_all(text.as_bytes(), " ".as_bytes());
-        self.value = Some(String::from_utf8(result.to_vec()).unwrap().trim().to_string());
+        self.value = Some(String::from_utf8(result.to_vec())?.trim().to_string());
+        Ok(())
     }
 
     pub fn value(&mut self) -> &str {
         if self.value.is_none() {
-            self.value = Some(String::from(self.tokens.iter().join(&self.split)))
+            self.value = Some(self.tokens.iter().join(&self.split))
         }
-        return &self.value.as_ref().unwrap()
+        self.value.as_ref().unwrap()
     }
 
     pub fn hashtags(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_hashtag()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_hashtag()).map(|x| x.value.clone()).collect::<Vec<String>>()
+    }
+
+    pub fn cashtags(&self) -> Vec<String> {
+        self.tokens.iter().filter(
+            |token| token.is_cashtag()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn mentions(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_mention()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_mention()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn emojis(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_emoji()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_emoji()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn emoticons(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_emoticon()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_emoticon()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn digits(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_digit()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_digit()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn emails(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_email()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_email()).map(|x| x.value.clone()).collect::<Vec<String>>()
     }
 
     pub fn urls(&self) -> Vec<String> {
-        return self.tokens.iter().filter(
-            |token| token.is_url()).map(
-                |x| x.clone()).map(|x| String::from(x.value)).collect::<Vec<String>>()
+        self.tokens.iter().filter(
+            |token| token.is_url()).map(|x| x.value.clone()).collect::<Vec<String>>()
+    }
+
+    /// Whether the raw text starts with the `RT @user: ` prefix of a retweet, whatever
+    /// `PrepConfig::retweet` did with it. Always false if the tokens weren't parsed from a text.
+    pub fn is_retweet(&self) -> bool {
+        self.retweeted_user().is_some()
+    }
+
+    /// The user of the `RT @user: ` prefix, as written in the raw text and without the '@', e.g. "bob".
+    pub fn retweeted_user(&self) -> Option<&str> {
+        let raw = self.alignment.as_ref()?.raw();
+        let m = RT_MENTION_PAT.find(raw.as_bytes()).ok()??;
+        // the prefix is "RT @" + user + ": "
+        Some(&raw[m.start() + 4..m.end() - 2])
     }
 }
 
@@ -128,91 +177,161 @@ impl IndexMut<usize> for ParsedText{
     }
 }
 
-fn _parse_text(
-    clean_text: String,
-    tokenizer: Option<fn(String) -> Vec<Token>>,
-    filters: Option<HashSet<&str>>,
-    emojis: Option<&str>,
-    emoticons_actions: Option<&str>,
-    mentions: Option<&str>,
-    hashtags: Option<&str>,
-    urls: Option<&str>,
-    digits: Option<&str>,
-    puncts: Option<&str>,
-    emails: Option<&str>,
-    html_tags: Option<&str>,
-) -> ParsedText {
-    let filters = filters.unwrap_or(HashSet::new());
-    let mut parsed_text = ParsedText {tokens: tokenizer
-        .unwrap_or(tweet_tokenize)(clean_text)
-        .iter()
-        .filter(|token| !filters.contains(token.value.as_str()))
-        .map(|x| Token{ value: (*x.value).to_string()})
+fn _parse_text(alignment: Alignment, config: &PrepConfig) -> Result<ParsedText, PrepError> {
+    let clean_text = alignment.text();
+    let mut tokens = config.tokenizer.tokenize(clean_text)?;
+    // A tokenizer may not give the spans, the tokens are then looked for in order in the text.
+    let mut cursor = 0;
+    for token in &mut tokens {
+        let range = match token.span {
+            Some(span) => Some((span.start, span.end)),
+            None => clean_text.get(cursor..)
+                .and_then(|rest| rest.find(token.value.as_str()))
+                .map(|i| (cursor + i, cursor + i + token.value.len())),
+        };
+        token.span = range.and_then(|(start, end)| {
+            cursor = end;
+            alignment.project(start, end)
+        });
+    }
+    let mut parsed_text = ParsedText {tokens: tokens
+        .into_iter()
+        .filter(|token| !config.filters.contains(token.value.as_str()))
         .collect::<_>(),
         split: String::from(" "),
-        value: None
+        value: None,
+        alignment: Some(alignment),
     };
-    parsed_text.process(
-        mentions,
-        hashtags,
-        urls,
-        digits,
-        emojis,
-        emoticons_actions,
-        puncts,
-        emails,
-        html_tags,
-    );
-    parsed_text.post_process();
-    return parsed_text
+    // before the actions, which could tag the tokens the slang expands
+    if let Some(slang) = &config.slang {
+        for token in &mut parsed_text.tokens {
+            slang.normalize(token, config.slang_mode)?;
+        }
+    }
+    parsed_text.process_with_config(config)?;
+    parsed_text.post_process()?;
+    Ok(parsed_text)
 }
 
-
+/// Preprocess the Tweet text before the tokenization, see `preprocess_text_with_config`.
+///
+/// # Panics
+///
+/// Panics on an unknown encoding or a matching error, use `preprocess_text_with_config` to get
+/// them as a `PrepError` instead.
+#[deprecated(note = "use `preprocess_text_with_config` instead")]
 pub fn preprocess_text(
-    mut text: String,
+    text: String,
     encoding: Option<&str>,
     remove_unencodable_char: Option<bool>,
     to_lower: Option<bool>,
     strip_accents: Option<bool>,
     reduce_len: Option<bool>
 ) -> String {
-    if encoding.is_some() {
-        if let Some(encoding_) = encoding_rs::Encoding::for_label(encoding.unwrap().as_bytes()) {
-            let (result, _encoding, _errors) = encoding_.encode(&text);
-            text = _encoding.decode(&result).0.to_string();
+    let config = PrepConfig {
+        encoding: encoding.map(String::from),
+        remove_unencodable_char: remove_unencodable_char.unwrap_or(false),
+        to_lower: to_lower.unwrap_or(true),
+        strip_accents: strip_accents.unwrap_or(false),
+        reduce_len: reduce_len.unwrap_or(false),
+        ..PrepConfig::default()
+    };
+    preprocess_text_with_config(text, &config).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Preprocess the Tweet text before the tokenization, see `PrepConfig` for the options.
+pub fn preprocess_text_with_config(text: String, config: &PrepConfig) -> Result<String, PrepError> {
+    Ok(preprocess_text_aligned(&text, config)?.text().to_string())
+}
+
+/// Preprocess the Tweet text like `preprocess_text_with_config`, keeping the alignment of the
+/// cleaned text with the raw text.
+pub fn preprocess_text_aligned(raw: &str, config: &PrepConfig) -> Result<Alignment, PrepError> {
+    let mut text = AlignedText::new(raw);
+    // "RT @user: " is looked for before the lowercasing, and tagged after it
+    let retweet = match config.retweet {
+        RetweetMode::Keep => None,
+        RetweetMode::Strip | RetweetMode::Tag => RT_MENTION_PAT.find(raw.as_bytes())?.map(|m| (m.start(), m.end())),
+    };
+    if let Some((_, end)) = retweet {
+        let mut stripped = AlignedText::with_capacity(raw.len() - end);
+        stripped.push_slice(&text, end, raw.len());
+        text = stripped;
+    }
+    // before the encoding, which would turn the typographic chars into '?'
+    if !config.typography.is_none() {
+        text = normalize_typography(&text, config.typography)?;
+    }
+    if let Some(encoding) = &config.encoding {
+        if let Some(encoding_) = encoding_rs::Encoding::for_label(encoding.as_bytes()) {
+            let (result, _encoding, _errors) = encoding_.encode(text.as_str());
+            if _encoding.decode(&result).0 != text.as_str() {
+                // Re-encode char by char to know where each '�' comes from.
+                text = text.map_chars(|c, output| {
+                    let mut buffer = [0; 4];
+                    let (result, _encoding, _errors) = encoding_.encode(c.encode_utf8(&mut buffer));
+                    output.push_str(&_encoding.decode(&result).0);
+                });
+            }
         }
-        text = if remove_unencodable_char.unwrap_or(false) {
-            text.replace(REPLACEMENT_CHARACTER, "")
+        text = if config.remove_unencodable_char {
+            text.map_chars(|c, output| if c != REPLACEMENT_CHARACTER { output.push(c) })
         } else {
             lazy_static! {
                 static ref RE: Regex = Regex::new(&format!(r#"{}{{2,}}$"#, REPLACEMENT_CHARACTER)).unwrap();
             }
-            let pattern: &Regex = &RE;
-            String::from_utf8(pattern.replace_all(text.as_bytes(), REPLACEMENT_CHARACTER.to_string().as_bytes()).to_vec()).unwrap()
+            text.replace_all(&RE, |caps, source, output| {
+                let m = caps.get(0).unwrap();
+                output.push_str(REPLACEMENT_CHARACTER.encode_utf8(&mut [0; 4]), source.span(m.start(), m.end()));
+            })?
         };
     }
-    if to_lower.unwrap_or(true) {
+    if config.to_lower {
         text = text.to_lowercase();
     }
-    if strip_accents.unwrap_or(false) {
-        text = strip_accents_unicode(&text).to_string();
+    if config.strip_accents {
+        text = text.map_chars(|c, output| unicode_normalization::char::decompose_canonical(c, |x| {
+            if !x.is_mark_nonspacing() {
+                output.push(x);
+            }
+        }));
     }
-    if reduce_len.unwrap_or(false) {
-        text = reduce_lengthening(&text);
+    if config.reduce_len {
+        text = reduce_lengthening(&text, config)?;
     }
-    text = remove_variation_selectors(&text);
+    text = text.map_chars(|c, output| if !VARIATION_SELECTORS.contains(&&*c.encode_utf8(&mut [0; 4])) { output.push(c) });
 
-    text = preprocess_url(&text);
+    text = text.replace_all(&HTTP_RE, |caps, source, output| {
+        let (before, scheme) = (caps.get(1).unwrap(), caps.get(2).unwrap());
+        output.push_slice(source, before.start(), before.end());
+        output.push_str(" ", source.span(scheme.start(), scheme.start()));
+        output.push_slice(source, scheme.start(), scheme.end());
+    })?;
 
     // c?est -> c'est
     lazy_static! {
         static ref REPEAT_RE: Regex = Regex::new(r#"(?:P<x>\w+)\?(?:P<y>\w+)"#).unwrap();
     }
-    let pattern: &Regex = &REPEAT_RE;
-    text = String::from_utf8(pattern.replace_all(text.as_bytes(), "$x'$y".as_bytes()).to_vec()).unwrap();
+    text = text.replace_all(&REPEAT_RE, |caps, source, output| {
+        let m = caps.get(0).unwrap();
+        let mut replacement = Vec::new();
+        caps.expand(b"$x'$y", &mut replacement);
+        output.push_str(&String::from_utf8_lossy(&replacement), source.span(m.start(), m.end()));
+    })?;
 
-    text = html_escape::decode_html_entities(&text).to_string();
-    return text
+    text = text.decode_html_entities();
+    // after the decoding, for "can&#39;t"
+    if let Some(contractions) = &config.contractions {
+        text = expand_contractions(&text, contractions)?;
+    }
+    if let (RetweetMode::Tag, Some((start, end))) = (config.retweet, retweet) {
+        let mut tagged = AlignedText::with_capacity(text.as_str().len() + 5);
+        tagged.push_str(&RT_TAG, (start, end));
+        tagged.push_str(" ", (end, end));
+        tagged.push_slice(&text, 0, text.as_str().len());
+        text = tagged;
+    }
+    Ok(text.into_alignment(raw))
 }
 
 /// Preprocess and parse the Tweet text
@@ -222,11 +341,12 @@ pub fn preprocess_text(
 /// * `text`: The input text to preprocess.
 /// * `encoding`: How do we encode the text, e.g., "utf-8".
 /// * `remove_unencodable_char`: In case of encoding error of a character it is replaced with '�'. This option allows removing the '�'.
-///     Otherwise a sequence of '�' is replaced by a single one, default to false.
+///   Otherwise a sequence of '�' is replaced by a single one, default to false.
 /// * `to_lower`: Whether to lowercase the text, default to true.
 /// * `strip_accents`: Whether to strip the accents, default to false.
-/// * `reduce_len`: Whether to remove repeated character sequences if it is repeated more than 4 times, default to false.
-/// * `tokenizer`: Function to tokenize the text (Converting `String` to `Vec<Token>`), default to `prep::tokenizer::tweet_tokenize`.
+/// * `reduce_len`: Whether to reduce the chars repeated more than `PrepConfig::max_repeat` times, 3 by default, e.g.
+///   "waaaaay" to "waaay", default to false.
+/// * `tokenizer`: How to tokenize the text, see `prep::tokenizer::Tokenizer`, default to `prep::tokenizer::TweetTokenizer`.
 /// * `filters`: A `HashSet` of tokens to filter out, default to None.
 /// * `emojis`: How to handle emojis, default to None.
 ///
@@ -234,7 +354,7 @@ pub fn preprocess_text(
 ///    * "remove": remove all emojis
 ///    * "tag": replaces the emoji by a tag `<EMOJI>`
 ///    * "demojize": replaces the emoji by its textual representation, e.g. :musical_keyboard:
-///     list of emojis: <https://www.webfx.com/tools/emoji-cheat-sheet/>
+///      list of emojis: <https://www.webfx.com/tools/emoji-cheat-sheet/>
 ///    * "emojize": replaces the emoji by its unicode representation, e.g. 😰
 /// * `emoticons`: How to handle emoticons, default to None.
 ///
@@ -278,6 +398,7 @@ pub fn preprocess_text(
 ///
 /// Example:
 /// ```
+/// # #![allow(deprecated)]
 /// use faster_tweet_nlp_toolkit::prep::text_parser::parse_text;
 /// let parsed_text = parse_text(
 ///     String::from("123 @hello #world www.url.com :) abc@gmail.com"),
@@ -298,7 +419,7 @@ pub fn preprocess_text(
 ///     Some("remove"), // emails
 ///     None,
 /// );
-/// // expect ParsedText { tokens: [Token { value: "@hello" }, Token { value: "#world" }], split: " ", value: Some("@hello #world") }
+/// // expect ParsedText { tokens: [Token { value: "@hello", kind: Mention }, Token { value: "#world", kind: Hashtag }], split: " ", value: Some("@hello #world") }
 ///
 /// parsed_text.hashtags();
 /// // expect ["#world"]
@@ -306,6 +427,13 @@ pub fn preprocess_text(
 /// parsed_text.mentions();
 /// // expect ["@hello"]
 /// ```
+///
+/// # Panics
+///
+/// Panics on an unknown action name, an unknown encoding or a matching error, use
+/// `parse_text_with_config` to get them as a `PrepError` instead.
+#[deprecated(note = "use `parse_text_with_config` instead")]
+#[allow(deprecated, clippy::too_many_arguments)]
 pub fn parse_text(
     text: String,
     encoding: Option<&str>,
@@ -313,7 +441,7 @@ pub fn parse_text(
     to_lower: Option<bool>,
     strip_accents: Option<bool>,
     reduce_len: Option<bool>,
-    tokenizer: Option<fn(String) -> Vec<Token>>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
     filters: Option<HashSet<&str>>,
     emojis: Option<&str>,
     emoticons: Option<&str>,
@@ -325,56 +453,216 @@ pub fn parse_text(
     emails: Option<&str>,
     html_tags: Option<&str>,
 ) -> ParsedText{
-    let clean_text = preprocess_text(text, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len);
-    _parse_text(clean_text, tokenizer, filters, emojis, emoticons, mentions, hashtags, urls, digits, puncts, emails, html_tags)
+    let config = PrepConfig {
+        encoding: encoding.map(String::from),
+        remove_unencodable_char: remove_unencodable_char.unwrap_or(false),
+        to_lower: to_lower.unwrap_or(true),
+        strip_accents: strip_accents.unwrap_or(false),
+        reduce_len: reduce_len.unwrap_or(false),
+        tokenizer: tokenizer.unwrap_or_else(|| Arc::new(TweetTokenizer)),
+        filters: filters.unwrap_or_default().into_iter().map(String::from).collect(),
+        emojis: parse_action_name(emojis),
+        emoticons: parse_action_name(emoticons),
+        mentions: parse_action_name(mentions),
+        hashtags: parse_action_name(hashtags),
+        urls: parse_action_name(urls),
+        digits: parse_action_name(digits),
+        puncts: parse_action_name(puncts),
+        emails: parse_action_name(emails),
+        html_tags: parse_action_name(html_tags),
+        ..PrepConfig::default()
+    };
+    parse_text_with_config(text, &config).unwrap_or_else(|err| panic!("{}", err))
 }
 
-fn reduce_lengthening(text: &str) -> String {
-    lazy_static! {
-        static ref LENGTHENING_RE: Regex = Regex::new(r#"(.)\1{2,}"#).unwrap();
+/// Preprocess and parse the Tweet text with the options of a `PrepConfig`.
+///
+/// Example:
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::text_parser::parse_text_with_config;
+/// use faster_tweet_nlp_toolkit::prep::token::ActionKind;
+/// let config = PrepConfig::builder().urls(ActionKind::Remove).emails(ActionKind::Remove).build().unwrap();
+/// let mut parsed_text = parse_text_with_config(String::from("@hello #world www.url.com abc@gmail.com"), &config).unwrap();
+/// parsed_text.value();
+/// // expect "@hello #world"
+/// ```
+pub fn parse_text_with_config(text: String, config: &PrepConfig) -> Result<ParsedText, PrepError> {
+    let alignment = preprocess_text_aligned(&text, config)?;
+    _parse_text(alignment, config)
+}
+
+/// Expand the contractions of the words, leaving the urls, emails, mentions and digits as they are.
+fn expand_contractions(text: &AlignedText, contractions: &Contractions) -> Result<AlignedText, PrepError> {
+    let protected = PROTECTED_TOKENS_RE.find_iter(text.as_str().as_bytes())
+        .map(|m| m.map(|m| (m.start(), m.end())))
+        .collect::<Result<Vec<_>, _>>()?;
+    text.replace_all(&CONTRACTION_PAT, |caps, source, output| {
+        let m = caps.get(0).unwrap();
+        let word = &source.as_str()[m.start()..m.end()];
+        let is_protected = protected.iter().any(|&(start, end)| m.start() < end && start < m.end());
+        match contractions.expand(word) {
+            Some(expansion) if !is_protected => output.push_str(&expansion, source.span(m.start(), m.end())),
+            _ => output.push_slice(source, m.start(), m.end()),
+        }
+    })
+}
+
+/// Replace the typographic variants of the categories of `typography` by their plain form.
+fn normalize_typography(text: &AlignedText, typography: Typography) -> Result<AlignedText, PrepError> {
+    let mut text = text.clone();
+    for (enabled, re, plain) in [(typography.quotes, &*QUOTES_PAT, "\""), (typography.apostrophes, &*APOSTROPHES_PAT, "'")] {
+        if enabled {
+            text = text.replace_all(re, |caps, source, output| {
+                let m = caps.get(0).unwrap();
+                output.push_str(plain, source.span(m.start(), m.end()));
+            })?;
+        }
+    }
+    Ok(text.map_chars(|c, output| match c {
+        '\u{2010}'..='\u{2015}' | '\u{2212}' if typography.dashes => output.push('-'),
+        '…' if typography.ellipsis => output.push_str("..."),
+        '\u{FF01}'..='\u{FF5E}' if typography.full_width => {
+            output.push(char::from_u32(c as u32 - 0xFEE0).unwrap())
+        }
+        '\u{00A0}' | '\u{2007}' | '\u{202F}' | '\u{3000}' if typography.spaces => output.push(' '),
+        // the zero-width joiner is left alone, as it glues the emojis of a sequence
+        '\u{200B}' | '\u{2060}' | '\u{FEFF}' if typography.spaces => {}
+        c => output.push(c),
+    }))
+}
+
+/// A run of the same char repeated `count` times at the bytes `start..end`.
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    start: usize,
+    end: usize,
+    c: char,
+    count: usize,
+}
+
+impl Run {
+    fn runs(text: &str) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for (i, c) in text.char_indices() {
+            match runs.last_mut() {
+                Some(run) if run.c == c => {
+                    run.end = i + c.len_utf8();
+                    run.count += 1;
+                }
+                _ => runs.push(Run { start: i, end: i + c.len_utf8(), c, count: 1 }),
+            }
+        }
+        runs
     }
-    let pattern: &Regex = &LENGTHENING_RE;
-    let mut res: String = text.to_string().clone();
-    for result in pattern.captures_iter(&text.as_bytes()) {
-        let captures = &result.unwrap();
-        let replace_from = &text[captures.get(0).unwrap().start()..captures.get(0).unwrap().end()];
-        let replace_to = &text[captures.get(1).unwrap().start()..captures.get(1).unwrap().end()].repeat(3);
-        res = res.replace(replace_from, replace_to);
+}
+
+/// Reduce the chars repeated more than `config.max_repeat` times, the last kept char covering the
+/// removed ones. With `config.vocabulary`, a lengthened word is rather reduced to a known word.
+fn reduce_lengthening(text: &AlignedText, config: &PrepConfig) -> Result<AlignedText, PrepError> {
+    let source = text.as_str();
+    let protected = if config.protect_tokens {
+        PROTECTED_TOKENS_RE.find_iter(source.as_bytes())
+            .map(|m| m.map(|m| (m.start(), m.end())))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        Vec::new()
+    };
+    let is_protected = |run: &Run| protected.iter().any(|&(start, end)| run.start < end && start < run.end);
+    let max_repeat = config.max_repeat.max(1);
+
+    let runs = Run::runs(source);
+    let mut kept: Vec<usize> = runs.iter()
+        .map(|run| if is_protected(run) { run.count } else { run.count.min(max_repeat) })
+        .collect();
+    if let Some(vocabulary) = &config.vocabulary {
+        // the words are the sequences of runs of alphanumeric chars
+        let mut start = 0;
+        while start < runs.len() {
+            let end = start + runs[start..].iter().take_while(|run| run.c.is_alphanumeric()).count().max(1);
+            let word = &runs[start..end];
+            if word[0].c.is_alphanumeric() && !word.iter().any(is_protected) {
+                if let Some(known) = reduce_to_vocabulary(source, word, vocabulary) {
+                    kept[start..end].copy_from_slice(&known);
+                }
+            }
+            start = end;
+        }
     }
-    res.to_string()
+
+    let mut output = AlignedText::with_capacity(source.len());
+    for (run, &kept) in runs.iter().zip(&kept) {
+        if kept == run.count {
+            output.push_slice(text, run.start, run.end);
+        } else {
+            let last = run.start + (kept - 1) * run.c.len_utf8();
+            output.push_slice(text, run.start, last);
+            output.push_str(&source[last..last + run.c.len_utf8()], text.span(last, run.end));
+        }
+    }
+    Ok(output)
+}
+
+/// The number of chars to keep from each run of a word to make a known word, trying to keep two
+/// then one of each char repeated three times or more; None if the word is not lengthened or no
+/// reduction is known.
+fn reduce_to_vocabulary(source: &str, word: &[Run], vocabulary: &HashSet<String>) -> Option<Vec<usize>> {
+    // enough for any real word, while bounding the 2^n candidates
+    const MAX_LENGTHENED_RUNS: usize = 8;
+    let lengthened: Vec<usize> = (0..word.len()).filter(|&i| word[i].count >= 3).collect();
+    if lengthened.is_empty() || lengthened.len() > MAX_LENGTHENED_RUNS
+        || vocabulary.contains(&source[word[0].start..word[word.len() - 1].end].to_lowercase()) {
+        return None;
+    }
+    (0..1usize << lengthened.len()).find_map(|mask| {
+        let mut kept: Vec<usize> = word.iter().map(|run| run.count).collect();
+        for (bit, &i) in lengthened.iter().enumerate() {
+            kept[i] = if mask & (1 << bit) == 0 { 2 } else { 1 };
+        }
+        let candidate: String = word.iter().zip(&kept)
+            .flat_map(|(run, &kept)| std::iter::repeat_n(run.c, kept))
+            .collect();
+        vocabulary.contains(&candidate.to_lowercase()).then_some(kept)
+    })
 }
 
 #[cfg(test)]
+#[allow(clippy::too_many_arguments)]
 mod tests {
     use std::{hash::Hash, vec};
 
     use rstest::rstest;
 
     use super::*;
+    use crate::prep::lexicon::Lexicon;
+    use crate::prep::token::TokenKind;
+    use crate::prep::segmenter::Segmenter;
+    use crate::prep::slang::Slang;
+    use crate::prep::stopwords::StopWords;
 
     fn _get_mock_parsed_text() -> ParsedText{
         let tokens = vec![
-                Token {value: String::from("<p>")},
-                Token {value: String::from("c\'est")},
-                Token {value: String::from("</p>")},
-                Token {value: String::from("@nlp")},
-                Token {value: String::from("https://www.google.fr")},
-                Token {value: String::from("cant")},
-                Token {value: String::from("wait")},
-                Token {value: String::from("😰")},
-                Token {value: String::from("for")},
-                Token {value: String::from("the")},
-                Token {value: String::from("new")},
-                Token {value: String::from("season")},
-                Token {value: String::from("of")},
-                Token {value: String::from("tutu@gmail.com")},
-                Token {value: String::from(r"\(^o^)/")},
-                Token {value: String::from("123")},
-                Token {value: String::from("!")},
-                Token {value: String::from("#davidlynch")},
-                Token {value: String::from("#tvseries")},
+                Token::new(String::from("<p>")),
+                Token::new(String::from("c\'est")),
+                Token::new(String::from("</p>")),
+                Token::new(String::from("@nlp")),
+                Token::new(String::from("https://www.google.fr")),
+                Token::new(String::from("cant")),
+                Token::new(String::from("wait")),
+                Token::new(String::from("😰")),
+                Token::new(String::from("for")),
+                Token::new(String::from("the")),
+                Token::new(String::from("new")),
+                Token::new(String::from("season")),
+                Token::new(String::from("of")),
+                Token::new(String::from("tutu@gmail.com")),
+                Token::new(String::from(r"\(^o^)/")),
+                Token::new(String::from("123")),
+                Token::new(String::from("!")),
+                Token::new(String::from("#davidlynch")),
+                Token::new(String::from("#tvseries")),
             ];
-            return ParsedText {tokens: tokens, split: " ".to_string(), value: None };
+            ParsedText {tokens, split: " ".to_string(), value: None, alignment: None }
         }
 
     #[test]
@@ -397,13 +685,161 @@ mod tests {
 
         // test post-processing
         parsed_text.value = Some(r" <p> c'est </p> @nlp     https://www.google.fr cant wait 😰 for the new season of tutu@gmail.com \(^o^)/ 123 ! #davidlynch #tvseries".to_owned());
-        parsed_text.post_process();
+        parsed_text.post_process().unwrap();
         assert_eq!(parsed_text.value(), r"<p> c'est </p> @nlp https://www.google.fr cant wait 😰 for the new season of tutu@gmail.com \(^o^)/ 123 ! #davidlynch #tvseries");
 
     }
 
+    #[rstest]
+    #[case(RetweetMode::Keep, "rt @bob : great game @alice", vec!["@bob", "@alice"])]
+    #[case(RetweetMode::Strip, "great game @alice", vec!["@alice"])]
+    #[case(RetweetMode::Tag, "<RT> great game @alice", vec!["@alice"])]
+    fn test_text_parser_retweet(#[case] mode: RetweetMode, #[case] expected: &str, #[case] mentions: Vec<&str>) {
+        let config = PrepConfig::builder().retweet(mode).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("RT @Bob: Great game @alice"), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+        assert_eq!(parsed_text.mentions(), mentions);
+        assert!(parsed_text.is_retweet());
+        assert_eq!(parsed_text.retweeted_user(), Some("Bob"));
+        let great = parsed_text.tokens.iter().find(|token| token.value == "great").unwrap();
+        assert_eq!(great.span.map(|span| (span.start, span.end)), Some((9, 14)));
+        if mode == RetweetMode::Tag {
+            // the tag covers the whole prefix
+            assert_eq!(parsed_text.tokens[0].span.map(|span| (span.start, span.end)), Some((0, 9)));
+        }
+    }
+
+    #[test]
+    fn test_text_parser_retweet_tag_not_html() {
+        let config = PrepConfig::builder().retweet(RetweetMode::Tag).html_tags(ActionKind::Remove).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("RT @bob: great <b>game</b>"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "<RT> great game");
+        assert_eq!(parsed_text.tokens[0].kind, TokenKind::Other);
+    }
+
+    #[rstest]
+    #[case(Typography::all(), "Don’t say “ｈｉ” – ever…\u{a0}ok\u{200b}!", "don't say \" hi \" - ever ... ok !")]
+    #[case(Typography { apostrophes: true, ..Typography::default() }, "Don’t say “hi” – ever…", "don't say “ hi ” – ever …")]
+    #[case(Typography { quotes: true, dashes: true, ..Typography::default() }, "« Oui » — d’accord", "\" oui \" - d ’ accord")]
+    #[case(Typography::default(), "Don’t ｈｉ", "don ’ t ｈｉ")]
+    #[case(Typography::all(), "👨\u{200d}👩\u{200d}👧 1−2", "👨 \u{200d} 👩 \u{200d} 👧 1-2")]
+    fn test_text_parser_typography(#[case] typography: Typography, #[case] text: &str, #[case] expected: &str) {
+        let config = PrepConfig::builder().typography(typography).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+    }
+
+    #[test]
+    fn test_text_parser_typography_alignment() {
+        let config = PrepConfig::builder().typography(Typography::all()).encoding("ascii").build().unwrap();
+        let text = "I’m ｆｉｎｅ…";
+        let parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        let values: Vec<&str> = parsed_text.tokens.iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(values, vec!["i'm", "fine", "..."]);
+        let raw: Vec<&str> = parsed_text.tokens.iter().map(|token| {
+            let span = token.span.unwrap();
+            &text[span.start..span.end]
+        }).collect();
+        assert_eq!(raw, vec!["I’m", "ｆｉｎｅ", "…"]);
+    }
+
+    #[rstest]
+    #[case("en", true, "I can't, won't and dont", "i can not , will not and do not")]
+    #[case("en", false, "Can't wait, I'm in! DON'T", "Can not wait , I am in ! DO NOT")]
+    #[case("en", true, "im sure u cant", "i am sure u can not")]
+    #[case("en", true, "#dont @cant www.cant.com bob@cant.com can&#39;t", "#dont @cant www.cant.com bob@cant.com can not")]
+    #[case("fr", true, "J'ai vu qu'il était là", "je ai vu que il était là")]
+    #[case("fr", false, "J’ai dit jusqu'ici", "Je ai dit jusque ici")]
+    fn test_text_parser_contractions(#[case] language: &str, #[case] to_lower: bool, #[case] text: &str, #[case] expected: &str) {
+        let contractions = Contractions::builtin(language).unwrap();
+        let config = PrepConfig::builder().contractions(contractions).to_lower(to_lower).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+    }
+
+    #[rstest]
+    #[case(SlangMode::Replace, "@u idk, u r sry lol #lol https://t.co/lol", "@u i do not know , you r sorry laughing out loud #lol https://t.co/lol")]
+    #[case(SlangMode::Append, "idk tbh", "idk i do not know tbh to be honest")]
+    #[case(SlangMode::Annotate, "idk tbh", "idk tbh")]
+    fn test_text_parser_slang(#[case] mode: SlangMode, #[case] text: &str, #[case] expected: &str) {
+        let config = PrepConfig::builder().slang(Slang::builtin("en").unwrap()).slang_mode(mode).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+        if mode == SlangMode::Annotate {
+            let expansions: Vec<Option<&str>> = parsed_text.tokens.iter().map(|token| token.expansion.as_deref()).collect();
+            assert_eq!(expansions, vec![Some("i do not know"), Some("to be honest")]);
+        }
+    }
+
+    #[rstest]
+    #[case("en", false, "This is NOT the best @the #the movie I've seen", "best @the #the movie seen")]
+    #[case("en", true, "This is NOT the best @the #the movie I've seen", "NOT best @the #the movie seen")]
+    #[case("fr", true, "Ce film ne est pas le meilleur", "film ne pas meilleur")]
+    fn test_text_parser_stopwords(#[case] language: &str, #[case] keep_negations: bool, #[case] text: &str, #[case] expected: &str) {
+        let config = PrepConfig::builder()
+            .stopwords(StopWords::builtin(language).unwrap())
+            .keep_negations(keep_negations)
+            .to_lower(false)
+            .build()
+            .unwrap();
+        let mut parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+    }
+
+    #[test]
+    fn test_process_with_stop_word_action() {
+        let mut parsed_text = _get_mock_parsed_text();
+        let config = PrepConfig { stopwords: Some(Arc::new(StopWords::new(["cant", "for"]))), ..PrepConfig::default() };
+        parsed_text.process_with_config(&config).unwrap();
+        assert!(!parsed_text.tokens.iter().any(|token| ["cant", "for"].contains(&token.value.as_str())));
+        let mut token = Token::new("The".to_string());
+        token.stop_word = true;
+        assert!(token.do_action(&Action::parse("remove", "is_stop_word").unwrap()));
+        assert_eq!(token.value, "");
+    }
+
+    #[test]
+    fn test_text_parser_contractions_alignment() {
+        let config = PrepConfig::builder().contractions(Contractions::new([("gonna", "going to")])).build().unwrap();
+        let text = "Gonna go";
+        let parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        let values: Vec<&str> = parsed_text.tokens.iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(values, vec!["going", "to", "go"]);
+        let raw: Vec<&str> = parsed_text.tokens.iter().map(|token| {
+            let span = token.span.unwrap();
+            &text[span.start..span.end]
+        }).collect();
+        assert_eq!(raw, vec!["Gonna", "Gonna", "go"]);
+    }
+
+    #[rstest]
+    #[case("great game")]
+    #[case("so RT @bob: great game")]
+    #[case("rt @bob: great game")]
+    fn test_text_parser_not_a_retweet(#[case] text: &str) {
+        let config = PrepConfig::builder().retweet(RetweetMode::Strip).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from(text), &config).unwrap();
+        assert_eq!(parsed_text.value(), text.to_lowercase().replace(':', " :"));
+        assert!(!parsed_text.is_retweet());
+        assert_eq!(parsed_text.retweeted_user(), None);
+        assert!(!_get_mock_parsed_text().is_retweet());
+    }
+
+    #[test]
+    fn test_parsed_text_serde_round_trip() {
+        let config = PrepConfig::builder().mentions(ActionKind::Tag).build().unwrap();
+        let parsed_text = parse_text_with_config(String::from("Héllo @World #Rust"), &config).unwrap();
+        let json = serde_json::to_string(&parsed_text).unwrap();
+        assert!(json.contains(r#""kind":"hashtag""#));
+        let restored: ParsedText = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, parsed_text);
+        assert_eq!(restored.tokens[0].span, parsed_text.tokens[0].span);
+        assert_eq!(restored.alignment.unwrap().project(0, 5), parsed_text.alignment.unwrap().project(0, 5));
+    }
+
     #[test]
     fn test_text_parser_when_there_is_emoji() {
+        #[allow(deprecated)]
         let mut parsed_text = parse_text(
             String::from("July @AlraashidS @shalsaeedi_ @asaldhferi @Fa3ix_ @iiBeba_ @_hajaraljble ❤️"),
             None,
@@ -426,10 +862,21 @@ mod tests {
         assert_eq!(parsed_text.value(), "july");
     }
 
+    #[test]
+    fn test_text_parser_when_there_is_emoji_with_config() {
+        let config = PrepConfig::builder().emojis(ActionKind::Remove).mentions(ActionKind::Remove).build().unwrap();
+        let mut parsed_text = parse_text_with_config(
+            String::from("July @AlraashidS @shalsaeedi_ @asaldhferi @Fa3ix_ @iiBeba_ @_hajaraljble ❤️"),
+            &config,
+        ).unwrap();
+        assert_eq!(parsed_text.value(), "july");
+    }
+
     #[rstest]
     #[case("remove", "asylum seeker :")]
     #[case("tag", "asylum seeker : <URL>")]
     fn test_text_parser_with_attached_url(#[case] url_action: &str, #[case] expected: &str) {
+        #[allow(deprecated)]
         let mut parsed_text = parse_text(
             String::from("asylum seeker:http://t.co/skU8zM7Slh"),
             None,
@@ -450,10 +897,14 @@ mod tests {
             None,
         );
         assert_eq!(parsed_text.value(), expected);
+        let config = PrepConfig::builder().urls(url_action.parse().unwrap()).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("asylum seeker:http://t.co/skU8zM7Slh"), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
     }
 
     #[test]
     fn test_text_parser_with_emoji_string() {
+        #[allow(deprecated)]
         let mut parsed_text = parse_text(
             String::from("@abc:joy:#hashtag"),
             None,
@@ -476,8 +927,16 @@ mod tests {
         assert_eq!(parsed_text.value(), "@abc 😂 #hashtag");
     }
 
+    #[test]
+    fn test_text_parser_with_emoji_string_with_config() {
+        let config = PrepConfig::builder().emojis(ActionKind::Emojize).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("@abc:joy:#hashtag"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "@abc 😂 #hashtag");
+    }
+
     #[test]
     fn test_text_parser_with_demojize() {
+        #[allow(deprecated)]
         let mut parsed_text = parse_text(
             String::from("@abc😂#hashtag"),
             None,
@@ -500,6 +959,13 @@ mod tests {
         assert_eq!(parsed_text.value(), "@abc :joy: #hashtag");
     }
 
+    #[test]
+    fn test_text_parser_with_demojize_with_config() {
+        let config = PrepConfig::builder().emojis(ActionKind::Demojize).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("@abc😂#hashtag"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "@abc :joy: #hashtag");
+    }
+
     #[rstest]
     #[case(None, None, None, None, None, None, None, None, None, r"<p> c'est </p> @nlp https://www.google.fr cant wait 😰 for the new season of tutu@gmail.com \(^o^)/ 123 ! #davidlynch #tvseries")]
     #[case(Some("tag"), None, None, None, None, None, None, None, None, r"<p> c'est </p> <MENTION> https://www.google.fr cant wait 😰 for the new season of tutu@gmail.com \(^o^)/ 123 ! #davidlynch #tvseries")]
@@ -530,6 +996,7 @@ mod tests {
         #[case] expected_value: &str,
     ) {
         let mut parsed_text = _get_mock_parsed_text();
+        #[allow(deprecated)]
         parsed_text.process(
             mentions_action,
             hashtags_action,
@@ -543,9 +1010,137 @@ mod tests {
         );
         assert_eq!(parsed_text.value(), expected_value);
     }
+
+    #[test]
+    fn test_text_parser_process_with_config() {
+        let mut parsed_text = _get_mock_parsed_text();
+        let config = PrepConfig::builder()
+            .mentions(ActionKind::Tag)
+            .urls(ActionKind::Remove)
+            .html_tags(ActionKind::Remove)
+            .build()
+            .unwrap();
+        parsed_text.process_with_config(&config).unwrap();
+        assert_eq!(parsed_text.value(), r"c'est <MENTION> cant wait 😰 for the new season of tutu@gmail.com \(^o^)/ 123 ! #davidlynch #tvseries");
+    }
+
+    #[test]
+    fn test_text_parser_with_action_not_allowed() {
+        let config = PrepConfig {hashtags: Some(ActionKind::Demojize), ..PrepConfig::default()};
+        let result = parse_text_with_config(String::from("#nlp"), &config);
+        assert!(matches!(result, Err(PrepError::Action(_))));
+    }
+
+    #[test]
+    fn test_text_parser_with_filters() {
+        let config = PrepConfig::builder().filters(["is", "for"]).build().unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("This is too much for you"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "this too much you");
+    }
+
+    #[rstest]
+    #[case(ActionKind::Tag, "<CASHTAG> up 5 % vs <CASHTAG>")]
+    #[case(ActionKind::Remove, "up 5 % vs")]
+    fn test_text_parser_with_cashtags(#[case] action: ActionKind, #[case] expected: &str) {
+        let text = String::from("$AAPL up 5% vs $MSFT");
+        let parsed_text = parse_text_with_config(text.clone(), &PrepConfig::default()).unwrap();
+        assert_eq!(parsed_text.cashtags(), vec!["$aapl", "$msft"]);
+        let config = PrepConfig::builder().cashtags(action).build().unwrap();
+        let mut parsed_text = parse_text_with_config(text, &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+    }
+
     #[rstest]
-    #[case("This is waaaaayyyy too much for you!!!!!!", "This is waaayyy too much for you!!!")]
-    fn test_reduce_lengthening(#[case] text: &str, #[case] expected: &str) {
-        assert_eq!(reduce_lengthening(text), expected);
+    #[case(true, "big fan of david lynch fan and climate change")]
+    #[case(false, "Big fan of David Lynch Fan and climate change")]
+    fn test_text_parser_with_segment(#[case] to_lower: bool, #[case] expected: &str) {
+        let config = PrepConfig::builder()
+            .to_lower(to_lower)
+            .hashtags(ActionKind::Segment)
+            .build()
+            .unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("Big fan of #DavidLynchFan and #climatechange"), &config).unwrap();
+        assert_eq!(parsed_text.value(), expected);
+    }
+
+    #[test]
+    fn test_text_parser_with_segmenter() {
+        let config = PrepConfig::builder()
+            .hashtags(ActionKind::Segment)
+            .segmenter(Segmenter::new([("rust", 10), ("acean", 2)]))
+            .build()
+            .unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("#rustacean #RustLang"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "rust acean rust lang");
+    }
+
+    #[test]
+    fn test_text_parser_with_tokenizer() {
+        let config = PrepConfig::builder()
+            .tokenizer(crate::prep::tokenizer::WhitespaceTokenizer)
+            .mentions(ActionKind::Tag)
+            .build()
+            .unwrap();
+        let mut parsed_text = parse_text_with_config(String::from("Hi @remy, c'est l'été"), &config).unwrap();
+        assert_eq!(parsed_text.value(), "hi @remy, c'est l'été");
+        let config = PrepConfig::builder()
+            .tokenizer(|text: String| text.split(',').map(|x| Token::new(x.trim().to_string())).collect())
+            .build()
+            .unwrap();
+        let parsed_text = parse_text_with_config(String::from("A b,C"), &config).unwrap();
+        assert_eq!(parsed_text.tokens.iter().map(|token| token.span.unwrap().start).collect::<Vec<_>>(), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_text_parser_spans() {
+        let raw = "Été @Remy:http://t.co/x &amp; Soooooo";
+        let config = PrepConfig::builder().strip_accents(true).reduce_len(true).mentions(ActionKind::Tag).build().unwrap();
+        let parsed_text = parse_text_with_config(String::from(raw), &config).unwrap();
+        let tokens: Vec<(&str, &str)> = parsed_text.tokens.iter()
+            .map(|token| (token.value.as_str(), &raw[token.span.unwrap().start..token.span.unwrap().end]))
+            .collect();
+        assert_eq!(tokens, vec![
+            ("ete", "Été"),
+            ("<MENTION>", "@Remy"),
+            (":", ":"),
+            ("http://t.co/x", "http://t.co/x"),
+            ("&", "&amp;"),
+            ("sooo", "Soooooo"),
+        ]);
+        assert_eq!(parsed_text.tokens[0].span.unwrap().char_end, 3);
+        let alignment = parsed_text.alignment.unwrap();
+        assert_eq!(alignment.text(), "ete @remy: http://t.co/x & sooo");
+        assert_eq!(alignment.project_chars(4, 10).map(|span| (span.char_start, span.char_end)), Some((4, 10)));
+    }
+
+    #[rstest]
+    #[case("This is waaaaayyyy too much for you!!!!!!", 3, true, "This is waaayyy too much for you!!!")]
+    #[case("aaaaa aaa!!!!", 2, true, "aa aa!!")]
+    #[case("1000000 likes on wwwww.example.com @maaaaark", 3, true, "1000000 likes on wwwww.example.com @maaaaark")]
+    #[case("1000000 likes on wwwww.example.com @maaaaark", 3, false, "1000 likes on www.example.com @maaark")]
+    #[case("ÉÉÉÉÉ", 1, true, "É")]
+    #[case("sooooo", 0, true, "so")]  // 0 is taken as 1
+    fn test_reduce_lengthening(#[case] text: &str, #[case] max_repeat: usize, #[case] protect_tokens: bool, #[case] expected: &str) {
+        let config = PrepConfig::builder().max_repeat(max_repeat).protect_tokens(protect_tokens).build().unwrap();
+        assert_eq!(reduce_lengthening(&AlignedText::new(text), &config).unwrap().as_str(), expected);
+    }
+
+    #[rstest]
+    #[case("sooooo goooood", "so good")]
+    #[case("Yesssss hellooooo", "Yes hello")]
+    #[case("cooool bruuuuh", "cool bruuuh")]
+    #[case("too goood", "too good")]
+    fn test_reduce_lengthening_with_vocabulary(#[case] text: &str, #[case] expected: &str) {
+        let config = PrepConfig::builder().vocabulary(["so", "good", "god", "yes", "hello", "cool", "too"]).build().unwrap();
+        assert_eq!(reduce_lengthening(&AlignedText::new(text), &config).unwrap().as_str(), expected);
+    }
+
+    #[test]
+    fn test_reduce_lengthening_alignment() {
+        let raw = "gooood";
+        let config = PrepConfig::builder().vocabulary(["good"]).build().unwrap();
+        let alignment = reduce_lengthening(&AlignedText::new(raw), &config).unwrap().into_alignment(raw);
+        assert_eq!(alignment.text(), "good");
+        assert_eq!(alignment.project(2, 3).map(|span| (span.start, span.end)), Some((2, 5)));
     }
 }
\ No newline at end of file
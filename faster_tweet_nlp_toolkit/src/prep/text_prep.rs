@@ -1,7 +1,23 @@
-use std::{collections::HashSet, fs::File};
-use std::io::{BufRead, BufReader, LineWriter, Write};
-use super::{text_parser::parse_text, token::Token};
+use std::{collections::{BTreeMap, HashSet}, sync::{Arc, Mutex, mpsc}, thread, time::{Duration, Instant}};
+use std::io::{BufRead, Write};
+use crate::error::PrepError;
+use super::compression::{create_output, open_input};
+#[allow(deprecated)]
+use super::config::parse_action_name;
+use super::config::PrepConfig;
+use super::file_format::{CsvQuoting, FileFormat};
+#[allow(deprecated)]
+use super::text_parser::parse_text;
+use super::{text_parser::parse_text_with_config, tokenizer::{Tokenizer, TweetTokenizer}};
 
+/// Preprocess the Tweet text and return the cleaned text, see `prep_with_config`.
+///
+/// # Panics
+///
+/// Panics on an unknown action name, an unknown encoding or a matching error, use
+/// `prep_with_config` to get them as a `PrepError` instead.
+#[deprecated(note = "use `prep_with_config` instead")]
+#[allow(deprecated, clippy::too_many_arguments)]
 pub fn prep(
     text: String,
     encoding: Option<&str>,
@@ -9,7 +25,7 @@ pub fn prep(
     to_lower: Option<bool>,
     strip_accents: Option<bool>,
     reduce_len: Option<bool>,
-    tokenizer: Option<fn(String) -> Vec<Token>>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
     filters: Option<HashSet<&str>>,
     emojis: Option<&str>,
     emoticons: Option<&str>,
@@ -21,7 +37,7 @@ pub fn prep(
     emails: Option<&str>,
     html_tags: Option<&str>,
 ) -> String {
-    return parse_text(
+    parse_text(
         text,
         encoding,
         remove_unencodable_char,
@@ -42,6 +58,53 @@ pub fn prep(
     ).value().to_string()
 }
 
+/// Preprocess the Tweet text with the options of a `PrepConfig` and return the cleaned text.
+pub fn prep_with_config(text: String, config: &PrepConfig) -> Result<String, PrepError> {
+    Ok(parse_text_with_config(text, config)?.value().to_string())
+}
+
+/// Preprocess texts like `prep_with_config` on `n_jobs` threads and return the results in order.
+///
+/// The texts are split into `n_jobs` contiguous parts, one per thread.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::text_prep::prep_batch_with_config;
+/// let texts = vec!["Hello".to_string(), "World".to_string()];
+/// let result = prep_batch_with_config(texts, &PrepConfig::default(), 2).unwrap();  // expect ["hello", "world"]
+/// ```
+pub fn prep_batch_with_config(texts: Vec<String>, config: &PrepConfig, n_jobs: usize) -> Result<Vec<String>, PrepError> {
+    let n_jobs = n_jobs.clamp(1, texts.len().max(1));
+    if n_jobs == 1 {
+        return texts.into_iter().map(|text| prep_with_config(text, config)).collect();
+    }
+    let part_size = texts.len().div_ceil(n_jobs);
+    let mut texts = texts.into_iter();
+    let parts: Vec<Vec<String>> = (0..n_jobs).map(|_| texts.by_ref().take(part_size).collect()).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = parts.into_iter()
+            .map(|part| scope.spawn(move || {
+                part.into_iter().map(|text| prep_with_config(text, config)).collect::<Result<Vec<_>, _>>()
+            }))
+            .collect();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.extend(handle.join().unwrap()?);
+        }
+        Ok(results)
+    })
+}
+
+/// Preprocess every line of `filename` and write the results to `outfile`, see `prep_file_with_config`.
+///
+/// # Panics
+///
+/// Panics on an unknown action name, an unknown encoding, a matching error or an I/O error, use
+/// `prep_file_with_config` to get them as a `PrepError` instead.
+#[deprecated(note = "use `prep_file_with_config` instead")]
+#[allow(deprecated, clippy::too_many_arguments)]
 pub fn prep_file(
     filename: &str,
     outfile: &str,
@@ -50,7 +113,7 @@ pub fn prep_file(
     to_lower: Option<bool>,
     strip_accents: Option<bool>,
     reduce_len: Option<bool>,
-    tokenizer: Option<fn(String) -> Vec<Token>>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
     filters: Option<HashSet<&str>>,
     emojis: Option<&str>,
     emoticons: Option<&str>,
@@ -62,35 +125,412 @@ pub fn prep_file(
     emails: Option<&str>,
     html_tags: Option<&str>,
 ) {
-    let reader: BufReader<File> = match File::open(filename) {
-        Err(why) => panic!("Couldn't open {}: {}", filename, why),
-        Ok(file) => BufReader::new(file),
+    let config = PrepConfig {
+        encoding: encoding.map(String::from),
+        remove_unencodable_char: remove_unencodable_char.unwrap_or(false),
+        to_lower: to_lower.unwrap_or(true),
+        strip_accents: strip_accents.unwrap_or(false),
+        reduce_len: reduce_len.unwrap_or(false),
+        tokenizer: tokenizer.unwrap_or_else(|| Arc::new(TweetTokenizer)),
+        filters: filters.unwrap_or_default().into_iter().map(String::from).collect(),
+        emojis: parse_action_name(emojis),
+        emoticons: parse_action_name(emoticons),
+        mentions: parse_action_name(mentions),
+        hashtags: parse_action_name(hashtags),
+        urls: parse_action_name(urls),
+        digits: parse_action_name(digits),
+        puncts: parse_action_name(puncts),
+        emails: parse_action_name(emails),
+        html_tags: parse_action_name(html_tags),
+        ..PrepConfig::default()
     };
-    let mut writer: LineWriter<File> = match File::create(outfile) {
-        Err(why) => panic!("Couldn't open {}: {}", outfile, why),
-        Ok(file) =>  LineWriter::new(file),
+    prep_file_with_config(filename, outfile, &config).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Preprocess every line of `filename` with the options of a `PrepConfig` and write the results to `outfile`.
+///
+/// With the cargo features `gzip`, `zstd` and `bzip2`, a compressed `filename` is decompressed and
+/// `outfile` is compressed according to its extension, see `prep::compression`.
+pub fn prep_file_with_config(filename: &str, outfile: &str, config: &PrepConfig) -> Result<(), PrepError> {
+    prep_file_with_options(filename, outfile, config, &FileOptions::default(), |_| true)
+}
+
+/// How `prep_file_with_options` reads the lines of a file and spreads them over threads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileOptions {
+    /// The format of the lines, default to plain text.
+    pub format: FileFormat,
+    /// The number of threads preprocessing the lines, default to the available parallelism.
+    pub threads: usize,
+    /// The number of lines given to a thread at once, default to 1000.
+    pub chunk_size: usize,
+}
+
+impl Default for FileOptions {
+    fn default() -> FileOptions {
+        FileOptions {
+            format: FileFormat::Text,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            chunk_size: 1000,
+        }
+    }
+}
+
+/// The progress of `prep_file_with_options`, reported after each chunk of lines written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    /// The number of lines written so far.
+    pub lines: usize,
+    /// The time since the start.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// The throughput so far.
+    pub fn lines_per_second(&self) -> f64 {
+        self.lines as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// The index of a chunk and its lines, or records, each with its line number in the file.
+type Chunk = (usize, Result<Vec<(usize, String)>, PrepError>);
+/// The index of a chunk, its preprocessed lines and the error stopping it, if any.
+type ChunkResult = (usize, Vec<String>, Option<PrepError>);
+
+/// Preprocess every line of `filename` like `prep_file_with_config`, on `options.threads` threads.
+///
+/// The lines are read by chunks of `options.chunk_size` lines, preprocessed in parallel and written
+/// in their original order, each according to `options.format`. A CSV file is read by records
+/// rather than lines, its header being written as is. `progress` is called after each chunk
+/// written, returning false stops the processing with `PrepError::Cancelled`. On an error, the
+/// lines before it are written.
+///
+/// # Example
+///
+/// ```no_run
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::file_format::{FileFormat, JsonlOptions};
+/// use faster_tweet_nlp_toolkit::prep::text_prep::{prep_file_with_options, FileOptions};
+/// let format = FileFormat::Jsonl(JsonlOptions { field: "full_text".to_string(), ..JsonlOptions::default() });
+/// let options = FileOptions { format, threads: 8, chunk_size: 10_000 };
+/// prep_file_with_options("tweets.jsonl", "clean.jsonl", &PrepConfig::default(), &options, |progress| {
+///     println!("{} lines, {:.0} lines/s", progress.lines, progress.lines_per_second());
+///     true
+/// }).unwrap();
+/// ```
+pub fn prep_file_with_options<F>(
+    filename: &str,
+    outfile: &str,
+    config: &PrepConfig,
+    options: &FileOptions,
+    progress: F,
+) -> Result<(), PrepError>
+where
+    F: FnMut(&Progress) -> bool,
+{
+    let reader = open_input(filename)?;
+    let mut output = create_output(outfile)?;
+    let result = prep_stream_with_options((reader, filename), (&mut output, outfile), config, options, progress);
+    // what is written before an error is kept
+    let finished = output.finish().map_err(|err| PrepError::io(outfile, err));
+    result.and(finished)
+}
+
+/// Preprocess every line of a reader like `prep_file_with_options`, writing the results to a writer,
+/// e.g. the standard input and output.
+///
+/// `input` and `output` are a reader and a writer with their names in the errors, e.g. "<stdin>".
+/// The writer is not flushed.
+pub fn prep_stream_with_options<R, W, F>(
+    input: (R, &str),
+    output: (&mut W, &str),
+    config: &PrepConfig,
+    options: &FileOptions,
+    mut progress: F,
+) -> Result<(), PrepError>
+where
+    R: BufRead + Send,
+    W: Write,
+    F: FnMut(&Progress) -> bool,
+{
+    let ((reader, filename), (writer, outfile)) = (input, output);
+    let delimiter = match &options.format {
+        FileFormat::Csv(csv) => Some(csv.delimiter),
+        _ => None,
     };
-    for line in reader.lines() {
-        let preprocessed_text = prep(
-            line.unwrap(),
-            encoding,
-            remove_unencodable_char,
-            to_lower,
-            strip_accents,
-            reduce_len,
-            tokenizer,
-            filters.clone(),
-            emojis,
-            emoticons,
-            mentions,
-            hashtags,
-            urls,
-            digits,
-            puncts,
-            emails,
-            html_tags,
-        );
-        writer.write_all(preprocessed_text.as_bytes()).unwrap();
-        writer.write("\n".as_bytes()).unwrap();
+    let mut records = Records::new(reader, filename, delimiter);
+    let format = match &options.format {
+        FileFormat::Csv(csv) if csv.header => match records.next().transpose()? {
+            Some((line, header)) => {
+                writer.write_all(header.as_bytes())
+                    .and_then(|_| writer.write_all(b"\n"))
+                    .map_err(|err| PrepError::io(outfile, err))?;
+                let csv = csv.with_header(&header)
+                    .map_err(|message| PrepError::Parse { path: filename.to_string(), line, message })?;
+                FileFormat::Csv(csv)
+            }
+            None => options.format.clone(),
+        },
+        format => format.clone(),
+    };
+    let format = &format;
+    let threads = options.threads.max(1);
+    let chunk_size = options.chunk_size.max(1);
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        // bounded, so that a slow writer doesn't let the whole file pile up in memory
+        let (chunk_sender, chunk_receiver) = mpsc::sync_channel::<Chunk>(2 * threads);
+        let (result_sender, result_receiver) = mpsc::channel::<ChunkResult>();
+        scope.spawn(move || read_chunks(records, chunk_size, chunk_sender));
+        // the workers own the receiver: once they are all gone, the reader stops too
+        let chunk_receiver = Arc::new(Mutex::new(chunk_receiver));
+        for _ in 0..threads {
+            let (chunk_receiver, result_sender) = (Arc::clone(&chunk_receiver), result_sender.clone());
+            scope.spawn(move || loop {
+                let received = chunk_receiver.lock().unwrap().recv();
+                let Ok((index, lines)) = received else { break };
+                let mut results = Vec::new();
+                let mut error = None;
+                match lines {
+                    Ok(lines) => for (line_number, line) in lines {
+                        match format.process(line, config, (filename, line_number)) {
+                            Ok(result) => results.push(result),
+                            Err(err) => {
+                                error = Some(err);
+                                break;
+                            }
+                        }
+                    },
+                    Err(err) => error = Some(err),
+                }
+                if result_sender.send((index, results, error)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_sender);
+
+        // write the chunks in order, keeping those arriving early
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let mut lines = 0;
+        for (index, results, error) in result_receiver {
+            pending.insert(index, (results, error));
+            while let Some((results, error)) = pending.remove(&next) {
+                for line in results {
+                    writer.write_all(line.as_bytes()).map_err(|err| PrepError::io(outfile, err))?;
+                    writer.write_all(b"\n").map_err(|err| PrepError::io(outfile, err))?;
+                    lines += 1;
+                }
+                if let Some(err) = error {
+                    return Err(err);
+                }
+                next += 1;
+                if !progress(&Progress { lines, elapsed: start.elapsed() }) {
+                    return Err(PrepError::Cancelled);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Send the records by chunks of `chunk_size` records, until the end of the file, an error, or the
+/// receiver being gone.
+fn read_chunks<R: BufRead>(records: Records<R>, chunk_size: usize, sender: mpsc::SyncSender<Chunk>) {
+    let mut chunk = Vec::with_capacity(chunk_size);
+    let mut index = 0;
+    for record in records {
+        match record {
+            Ok(record) => chunk.push(record),
+            Err(err) => {
+                // the lines read so far go first, then the error
+                if !chunk.is_empty() {
+                    let _ = sender.send((index, Ok(std::mem::take(&mut chunk))));
+                    index += 1;
+                }
+                let _ = sender.send((index, Err(err)));
+                return;
+            }
+        }
+        if chunk.len() == chunk_size {
+            if sender.send((index, Ok(std::mem::replace(&mut chunk, Vec::with_capacity(chunk_size))))).is_err() {
+                return;
+            }
+            index += 1;
+        }
+    }
+    if !chunk.is_empty() {
+        let _ = sender.send((index, Ok(chunk)));
+    }
+}
+
+/// The records of a file with the number of their first line: its lines, or, for a CSV file with
+/// the `delimiter`, as many lines as a record spans, its quoted fields may contain newlines.
+struct Records<'a, R> {
+    reader: R,
+    filename: &'a str,
+    delimiter: Option<char>,
+    line_number: usize,
+    buffer: Vec<u8>,
+}
+
+impl<'a, R: BufRead> Records<'a, R> {
+    fn new(reader: R, filename: &'a str, delimiter: Option<char>) -> Records<'a, R> {
+        Records { reader, filename, delimiter, line_number: 0, buffer: Vec::new() }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<'_, R> {
+    type Item = Result<(usize, String), PrepError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.clear();
+        let start = self.line_number + 1;
+        // a newline ends a record unless it is within a quoted field
+        let mut quoting = CsvQuoting::default();
+        loop {
+            let end = self.buffer.len();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => break,
+                Ok(_) => self.line_number += 1,
+                Err(err) => return Some(Err(PrepError::io(self.filename, err))),
+            }
+            let Some(delimiter) = self.delimiter else {
+                break;
+            };
+            if quoting.read_line(&String::from_utf8_lossy(&self.buffer[end..]), delimiter) {
+                break;
+            }
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let record = match std::str::from_utf8(&self.buffer) {
+            Ok(record) => record,
+            Err(err) => return Some(Err(PrepError::Utf8 { line: Some(start), source: err })),
+        };
+        let record = record.strip_suffix('\n').map(|x| x.strip_suffix('\r').unwrap_or(x)).unwrap_or(record);
+        Some(Ok((start, record.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::prep::token::ActionKind;
+    use crate::prep::file_format::{CsvColumn, CsvOptions, JsonlOptions};
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_prep_with_config() {
+        let config = PrepConfig::builder().urls(ActionKind::Remove).digits(ActionKind::Tag).build().unwrap();
+        assert_eq!(prep_with_config(String::from("123 www.url.com"), &config).unwrap(), "<DIGIT>");
+    }
+
+    #[rstest]
+    #[case(0, 5)]
+    #[case(3, 10)]
+    #[case(8, 3)]
+    #[case(4, 0)]
+    fn test_prep_batch_with_config(#[case] n_jobs: usize, #[case] n_texts: usize) {
+        let texts: Vec<String> = (0..n_texts).map(|i| format!("Tweet #{} @user{}", i, i)).collect();
+        let config = PrepConfig::builder().mentions(ActionKind::Remove).build().unwrap();
+        let expected: Vec<String> = (0..n_texts).map(|i| format!("tweet #{}", i)).collect();
+        assert_eq!(prep_batch_with_config(texts, &config, n_jobs).unwrap(), expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_prep_file_with_config() {
+        let dir = TestDir::new("prep_file");
+        let (filename, outfile) = (dir.path("input.txt"), dir.path("output.txt"));
+        fs::write(&filename, "Hello @remy\r\nwww.url.com :)\n").unwrap();
+        let config = PrepConfig::builder().mentions(ActionKind::Tag).urls(ActionKind::Remove).build().unwrap();
+        prep_file_with_config(&filename, &outfile, &config).unwrap();
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), "hello <MENTION>\n:)\n");
+    }
+
+    #[test]
+    fn test_prep_file_with_config_errors() {
+        let dir = TestDir::new("prep_file_errors");
+        let outfile = dir.path("output.txt");
+        let result = prep_file_with_config("does/not/exist.txt", &outfile, &PrepConfig::default());
+        assert!(matches!(result, Err(PrepError::Io { path, .. }) if path == "does/not/exist.txt"));
+
+        let filename = dir.path("input.txt");
+        fs::write(&filename, b"ok\n\xff\n").unwrap();
+        let result = prep_file_with_config(&filename, &outfile, &PrepConfig::default());
+        assert!(matches!(result, Err(PrepError::Utf8 { line: Some(2), .. })));
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), "ok\n");
+    }
+
+    #[rstest]
+    #[case(1, 1)]
+    #[case(4, 3)]
+    #[case(3, 1000)]
+    fn test_prep_file_with_options(#[case] threads: usize, #[case] chunk_size: usize) {
+        let dir = TestDir::new(&format!("prep_file_options_{}_{}", threads, chunk_size));
+        let (filename, outfile) = (dir.path("input.txt"), dir.path("output.txt"));
+        let input: String = (0..100).map(|i| format!("Tweet #{} @user{}\n", i, i)).collect();
+        fs::write(&filename, input).unwrap();
+        let config = PrepConfig::builder().mentions(ActionKind::Remove).build().unwrap();
+        let options = FileOptions { threads, chunk_size, ..FileOptions::default() };
+        let mut reported = Vec::new();
+        prep_file_with_options(&filename, &outfile, &config, &options, |progress| {
+            reported.push(progress.lines);
+            true
+        }).unwrap();
+        let expected: String = (0..100).map(|i| format!("tweet #{}\n", i)).collect();
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), expected);
+        assert_eq!(reported.len(), 100usize.div_ceil(chunk_size));
+        assert_eq!(reported.last(), Some(&100));
+    }
+
+    #[test]
+    fn test_prep_file_with_options_cancelled() {
+        let dir = TestDir::new("prep_file_cancelled");
+        let (filename, outfile) = (dir.path("input.txt"), dir.path("output.txt"));
+        fs::write(&filename, "a\nb\nc\nd\n").unwrap();
+        let options = FileOptions { threads: 2, chunk_size: 1, ..FileOptions::default() };
+        let result = prep_file_with_options(&filename, &outfile, &PrepConfig::default(), &options, |progress| progress.lines < 2);
+        assert!(matches!(result, Err(PrepError::Cancelled)));
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), "a\nb\n");
+    }
+
+    #[test]
+    fn test_prep_file_with_options_jsonl() {
+        let dir = TestDir::new("prep_file_jsonl");
+        let (filename, outfile) = (dir.path("input.jsonl"), dir.path("output.jsonl"));
+        fs::write(&filename, "{\"id\":1,\"text\":\"Hi @Bob\"}\n{\"id\":2,\"text\":\"Bye\"}\n{\"id\":3,\n").unwrap();
+        let format = FileFormat::Jsonl(JsonlOptions { output_field: None, ..JsonlOptions::default() });
+        let options = FileOptions { format, threads: 2, chunk_size: 2 };
+        let result = prep_file_with_options(&filename, &outfile, &PrepConfig::default(), &options, |_| true);
+        assert!(matches!(result, Err(PrepError::Parse { line: 3, .. })));
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), "{\"id\":1,\"text\":\"hi @bob\"}\n{\"id\":2,\"text\":\"bye\"}\n");
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn test_prep_file_with_options_csv(#[case] chunk_size: usize) {
+        let dir = TestDir::new(&format!("prep_file_csv_{}", chunk_size));
+        let (filename, outfile) = (dir.path("input.csv"), dir.path("output.csv"));
+        fs::write(&filename, "id,tweet,label\r\n1,\"Hi\n@Bob\",pos\n2,Bye 5\" screen,neg\n3,\"So, \"\"great\"\"\n\n!\",pos\n4,\"oops,neg\n").unwrap();
+        let format = FileFormat::Csv(CsvOptions { column: CsvColumn::Name("tweet".to_string()), ..CsvOptions::default() });
+        let options = FileOptions { format, threads: 2, chunk_size };
+        let result = prep_file_with_options(&filename, &outfile, &PrepConfig::default(), &options, |_| true);
+        assert!(matches!(result, Err(PrepError::Parse { line: 8, .. })));
+        let expected = "id,tweet,label\n1,hi @bob,pos\n2,\"bye 5 \"\" screen\",neg\n3,\"so , \"\" great \"\" !\",pos\n";
+        assert_eq!(fs::read_to_string(&outfile).unwrap(), expected);
+
+        let format = FileFormat::Csv(CsvOptions { column: CsvColumn::Name("text".to_string()), ..CsvOptions::default() });
+        let options = FileOptions { format, threads: 2, chunk_size };
+        let result = prep_file_with_options(&filename, &outfile, &PrepConfig::default(), &options, |_| true);
+        assert_eq!(result.unwrap_err().to_string(), format!(r#"{}:1: no column "text" in the header"#, filename));
+    }
+}
@@ -1,20 +1,155 @@
 #![allow(dead_code, unused)]
 #![feature(const_mut_refs)]
 use std::fmt::Display;
+use std::str::FromStr;
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
 
 use pcre2::bytes::Regex;
+use serde::{Deserialize, Serialize};
 use crate::prep::regexes::*;
 use crate::constants::*;
+use crate::error::PrepError;
+use crate::prep::segmenter::{Segmenter, DEFAULT_SEGMENTER};
 use unicode_categories::UnicodeCategories;
 use emojis;
 use lazy_static::lazy_static;
 use rstest::rstest;
 
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+/// The kind of a token, computed once when the token is created or its value is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Url,
+    Email,
+    Mention,
+    Hashtag,
+    /// A stock or crypto symbol, e.g. `$AAPL`.
+    Cashtag,
+    Emoticon,
+    HtmlTag,
+    Digit,
+    /// A unicode emoji, e.g. `😂`, or an emoji alias, e.g. `:joy:`.
+    Emoji,
+    /// A token made only of punctuations.
+    Punct,
+    Word,
+    #[default]
+    Other,
+}
+
+impl TokenKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKind::Url => "url",
+            TokenKind::Email => "email",
+            TokenKind::Mention => "mention",
+            TokenKind::Hashtag => "hashtag",
+            TokenKind::Cashtag => "cashtag",
+            TokenKind::Emoticon => "emoticon",
+            TokenKind::HtmlTag => "html_tag",
+            TokenKind::Digit => "digit",
+            TokenKind::Emoji => "emoji",
+            TokenKind::Punct => "punct",
+            TokenKind::Word => "word",
+            TokenKind::Other => "other",
+        }
+    }
+
+    /// Classify a value with the anchored regexes, in the order of the tokenizer pipeline.
+    pub fn classify(value: &str) -> Result<TokenKind, PrepError> {
+        if value.is_empty() {
+            return Ok(TokenKind::Other)
+        }
+        let bytes = value.as_bytes();
+        let kind = if value == *RT_TAG {
+            TokenKind::Other
+        } else if URL_RE.is_match(bytes)? {
+            TokenKind::Url
+        } else if EMAIL_RE.is_match(bytes)? {
+            TokenKind::Email
+        } else if MENTION_RE.is_match(bytes)? {
+            TokenKind::Mention
+        } else if HASHTAG_RE.is_match(bytes)? && !NOT_A_HASHTAG_RE.is_match(bytes)? {
+            TokenKind::Hashtag
+        } else if CASHTAG_RE.is_match(bytes)? {
+            TokenKind::Cashtag
+        } else if EMOTICONS_RE.is_match(bytes)? {
+            TokenKind::Emoticon
+        } else if HTML_TAG_RE.is_match(bytes)? {
+            TokenKind::HtmlTag
+        } else if DIGIT_RE.is_match(bytes)? {
+            TokenKind::Digit
+        } else if WORD_RE.is_match(bytes)? {
+            TokenKind::Word
+        } else {
+            TokenKind::from_value(value)
+        };
+        Ok(kind)
+    }
+
+    /// The kind of a token matched by the named group `group` of the tokenizer pipeline.
+    pub fn from_pipeline_group(group: &str, value: &str) -> Result<TokenKind, PrepError> {
+        let kind = match group {
+            // matched by the html_tag pattern
+            _ if value == *RT_TAG => TokenKind::Other,
+            "url" => TokenKind::Url,
+            "email" => TokenKind::Email,
+            "mention" => TokenKind::Mention,
+            // a hashtag can't be just a seq of numbers
+            "hashtag" if NOT_A_HASHTAG_RE.is_match(value.as_bytes())? => TokenKind::Other,
+            "hashtag" => TokenKind::Hashtag,
+            "cashtag" => TokenKind::Cashtag,
+            "emoticon" => TokenKind::Emoticon,
+            "html_tag" => TokenKind::HtmlTag,
+            "digit" => TokenKind::Digit,
+            "word" => TokenKind::Word,
+            _ => TokenKind::from_value(value),
+        };
+        Ok(kind)
+    }
+
+    fn from_value(value: &str) -> TokenKind {
+        if _is_unicode_emoji(value) || _is_emoji_alias(value) {
+            TokenKind::Emoji
+        } else if _is_punct(value) {
+            TokenKind::Punct
+        } else {
+            TokenKind::Other
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The position of a token as a byte range `start..end` and a char range `char_start..char_end`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
 pub struct Token{
     pub value: String,
+    /// The kind of the token, kept up to date by `set_value`.
+    pub kind: TokenKind,
+    /// Where the token comes from: the tokenizer gives its position in the text it tokenized, which
+    /// `parse_text_with_config` projects onto the raw text. None for a token created by hand.
+    pub span: Option<Span>,
+    /// The expansion of a slang token, e.g. "i do not know" for "idk", see `prep::slang::Slang`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expansion: Option<String>,
+    /// Whether the token is a stop word of `PrepConfig::stopwords`, set before the actions are applied.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stop_word: bool,
 }
 
 impl Display for Token{
@@ -29,166 +164,456 @@ fn _is_punct(value: &str) -> bool {
 }
 
 fn _is_emoji_alias(value: &str) -> bool {
-    if (value.len() <= 2 || ! (value.starts_with(":") && value.ends_with(":"))) {
+    if value.len() <= 2 || ! (value.starts_with(':') && value.ends_with(':')) {
         return false
     }
-    let emoji_opt = emojis::get_by_shortcode(&value[1..value.len()-1]);
-    match emoji_opt {
-        Some(_emoji) => true,
-        _ => false
-    }
+    emojis::get_by_shortcode(&value[1..value.len()-1]).is_some()
 }
 
 fn _is_unicode_emoji(value: &str) -> bool {
-    let emoji_opt = emojis::get(value);
-    match emoji_opt {
-        Some(_emoji) => true,
-        _ => false
-    }
+    emojis::get(value).is_some()
 }
 
 impl Token{
+    /// Create a token, classifying its value with the regexes; a matching error gives `TokenKind::Other`,
+    /// use `try_new` to get it instead.
     pub fn new(value: String) -> Self{
-        Self { value: value }
+        let kind = TokenKind::classify(&value).unwrap_or_default();
+        Self::with_kind(value, kind)
     }
 
+    /// Create a token like `new`, failing on a matching error of the classification.
+    pub fn try_new(value: String) -> Result<Self, PrepError>{
+        let kind = TokenKind::classify(&value)?;
+        Ok(Self::with_kind(value, kind))
+    }
+
+    /// Create a token whose kind is already known, e.g. from the tokenizer.
+    pub fn with_kind(value: String, kind: TokenKind) -> Self{
+        Self { value, kind, span: None, expansion: None, stop_word: false }
+    }
+
+    /// Set the position of the token.
+    pub fn with_span(mut self, span: Span) -> Self{
+        self.span = Some(span);
+        self
+    }
+
+    /// Set the value and classify it again, the span is kept as the token still comes from there.
+    ///
+    /// A matching error gives `TokenKind::Other`, use `try_set_value` to get it instead.
     pub fn set_value(&mut self, new_value: String) {
+        self.kind = TokenKind::classify(&new_value).unwrap_or_default();
         self.value = new_value;
     }
 
+    /// Set the value like `set_value`, failing on a matching error, which leaves the token unchanged.
+    pub fn try_set_value(&mut self, new_value: String) -> Result<(), PrepError> {
+        self.kind = TokenKind::classify(&new_value)?;
+        self.value = new_value;
+        Ok(())
+    }
+
     pub fn index_at(&mut self, i: usize) -> char {
         self.value.chars().nth(i).unwrap()
     }
 
     pub fn is_emoji(&self) -> bool {
-        _is_unicode_emoji(&self.value) || _is_emoji_alias(&self.value)
+        self.kind == TokenKind::Emoji
     }
 
+    /// Whether the token is made only of punctuations, which includes emoticons like ":)".
     pub fn is_punct(&self) -> bool {
-        _is_punct(&self.value)
+        match self.kind {
+            TokenKind::Punct => true,
+            TokenKind::Emoticon => _is_punct(&self.value),
+            _ => false,
+        }
     }
 
-    pub fn check_flag(&self, re: &Regex) -> bool {
-        re.is_match(&self.value.as_bytes()).unwrap()
+    pub fn check_flag(&self, re: &Regex) -> Result<bool, PrepError> {
+        Ok(re.is_match(self.value.as_bytes())?)
     }
 
     pub fn is_hashtag(&self) -> bool {
-        !self.check_flag(&NOT_A_HASHTAG_RE) & self.check_flag(&HASHTAG_RE)
+        self.kind == TokenKind::Hashtag
+    }
+
+    pub fn is_cashtag(&self) -> bool {
+        self.kind == TokenKind::Cashtag
     }
 
     pub fn is_url(&self) -> bool {
-        self.check_flag(&URL_RE)
+        self.kind == TokenKind::Url
     }
 
     pub fn is_mention(&self) -> bool {
-        self.check_flag(&MENTION_RE)
+        self.kind == TokenKind::Mention
     }
 
     pub fn is_digit(&self) -> bool {
-        self.check_flag(&DIGIT_RE)
+        self.kind == TokenKind::Digit
     }
 
     pub fn is_email(&self) -> bool {
-        self.check_flag(&EMAIL_RE)
+        self.kind == TokenKind::Email
     }
 
     pub fn is_html_tag(&self) -> bool {
-        self.check_flag(&HTML_TAG_RE)
+        self.kind == TokenKind::HtmlTag
     }
 
     pub fn is_emoticon(&self) -> bool {
-        self.check_flag(&EMOTICONS_RE)
+        self.kind == TokenKind::Emoticon
+    }
+
+    pub fn is_word(&self) -> bool {
+        self.kind == TokenKind::Word
+    }
+
+    pub fn is_stop_word(&self) -> bool {
+        self.stop_word
     }
 
     pub fn do_action(&mut self, action: &Action) -> bool {
         action.apply(self)
     }
+
+    pub fn try_do_action(&mut self, action: &Action) -> Result<bool, PrepError> {
+        action.try_apply(self)
+    }
+}
+
+/// What to do with a token matching a [`TokenCondition`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    /// Delete the token.
+    Remove,
+    /// Replace the token by the tag of its condition, e.g. `<HASHTAG>`.
+    Tag,
+    /// Replace the emoji by its textual representation, e.g. `:joy:`.
+    Demojize,
+    /// Replace the textual representation of an emoji by the emoji, e.g. `😂`.
+    Emojize,
+    /// Split the hashtag into its words, e.g. `#DavidLynchFan` into `david lynch fan`.
+    Segment,
+}
+
+impl ActionKind {
+    pub const ALL: [ActionKind; 5] = [ActionKind::Remove, ActionKind::Tag, ActionKind::Demojize, ActionKind::Emojize, ActionKind::Segment];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionKind::Remove => "remove",
+            ActionKind::Tag => "tag",
+            ActionKind::Demojize => "demojize",
+            ActionKind::Emojize => "emojize",
+            ActionKind::Segment => "segment",
+        }
+    }
+}
+
+impl Display for ActionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ActionKind {
+    type Err = ActionError;
+
+    fn from_str(s: &str) -> Result<ActionKind, ActionError> {
+        ActionKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| ActionError::UnknownAction(s.to_string()))
+    }
+}
+
+/// The kind of token an [`Action`] applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenCondition {
+    Mention,
+    Hashtag,
+    Cashtag,
+    Url,
+    Digit,
+    Emoji,
+    Emoticon,
+    Punct,
+    Email,
+    HtmlTag,
+    /// A stop word, see `Token::stop_word`.
+    StopWord,
+}
+
+impl TokenCondition {
+    pub const ALL: [TokenCondition; 11] = [
+        TokenCondition::Mention,
+        TokenCondition::Hashtag,
+        TokenCondition::Cashtag,
+        TokenCondition::Url,
+        TokenCondition::Digit,
+        TokenCondition::Emoji,
+        TokenCondition::Emoticon,
+        TokenCondition::Punct,
+        TokenCondition::Email,
+        TokenCondition::HtmlTag,
+        TokenCondition::StopWord,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenCondition::Mention => "is_mention",
+            TokenCondition::Hashtag => "is_hashtag",
+            TokenCondition::Cashtag => "is_cashtag",
+            TokenCondition::Url => "is_url",
+            TokenCondition::Digit => "is_digit",
+            TokenCondition::Emoji => "is_emoji",
+            TokenCondition::Emoticon => "is_emoticon",
+            TokenCondition::Punct => "is_punct",
+            TokenCondition::Email => "is_email",
+            TokenCondition::HtmlTag => "is_html_tag",
+            TokenCondition::StopWord => "is_stop_word",
+        }
+    }
+
+    /// The actions that can be applied to a token matching this condition.
+    pub fn allowed_actions(&self) -> &'static [ActionKind] {
+        match self {
+            TokenCondition::Emoji => &[ActionKind::Remove, ActionKind::Tag, ActionKind::Demojize, ActionKind::Emojize],
+            TokenCondition::Hashtag => &[ActionKind::Remove, ActionKind::Tag, ActionKind::Segment],
+            TokenCondition::HtmlTag | TokenCondition::StopWord => &[ActionKind::Remove],
+            _ => &[ActionKind::Remove, ActionKind::Tag],
+        }
+    }
+
+    /// The tag replacing a token matching this condition, if the condition can be tagged.
+    pub fn tag(&self) -> Option<&'static str> {
+        match self {
+            TokenCondition::Mention => Some(*MENTION_TAG),
+            TokenCondition::Hashtag => Some(*HASHTAG_TAG),
+            TokenCondition::Cashtag => Some(*CASHTAG_TAG),
+            TokenCondition::Url => Some(*URL_TAG),
+            TokenCondition::Digit => Some(*DIGIT_TAG),
+            TokenCondition::Emoji => Some(*EMOJI_TAG),
+            TokenCondition::Emoticon => Some(*EMOTICON_TAG),
+            TokenCondition::Punct => Some(*PUNCTUATION_TAG),
+            TokenCondition::Email => Some(*EMAIL_TAG),
+            TokenCondition::HtmlTag | TokenCondition::StopWord => None,
+        }
+    }
+
+    /// Whether the token matches this condition, read from the kind of the token.
+    pub fn matches(&self, token: &Token) -> bool {
+        match self {
+            TokenCondition::Mention => token.is_mention(),
+            TokenCondition::Hashtag => token.is_hashtag(),
+            TokenCondition::Cashtag => token.is_cashtag(),
+            TokenCondition::Url => token.is_url(),
+            TokenCondition::Digit => token.is_digit(),
+            TokenCondition::Emoji => token.is_emoji(),
+            TokenCondition::Emoticon => token.is_emoticon(),
+            TokenCondition::Punct => token.is_punct(),
+            TokenCondition::Email => token.is_email(),
+            TokenCondition::HtmlTag => token.is_html_tag(),
+            TokenCondition::StopWord => token.is_stop_word(),
+        }
+    }
+}
+
+impl Display for TokenCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
+impl FromStr for TokenCondition {
+    type Err = ActionError;
+
+    fn from_str(s: &str) -> Result<TokenCondition, ActionError> {
+        TokenCondition::ALL
+            .into_iter()
+            .find(|condition| condition.as_str() == s)
+            .ok_or_else(|| ActionError::UnknownCondition(s.to_string()))
+    }
+}
+
+/// An error raised when an action name, a condition name or their combination is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionError {
+    UnknownAction(String),
+    UnknownCondition(String),
+    NotAllowed {
+        action: ActionKind,
+        condition: TokenCondition,
+    },
+}
+
+fn _join<T: Display>(items: &[T]) -> String {
+    items.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl Display for ActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionError::UnknownAction(action) => write!(
+                f, r#"Unknown action "{}", expected one of {}"#, action, _join(&ActionKind::ALL)),
+            ActionError::UnknownCondition(condition) => write!(
+                f, r#"Unknown condition "{}", expected one of {}"#, condition, _join(&TokenCondition::ALL)),
+            ActionError::NotAllowed { action, condition } => write!(
+                f, r#"Action "{}" is not allowed for {}, expected one of {}"#, action, condition, _join(condition.allowed_actions())),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+/// An action applied to the tokens matching a condition, e.g. tagging the hashtags.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::token::{Action, ActionKind, Token, TokenCondition};
+/// let action = Action::new(ActionKind::Tag, TokenCondition::Hashtag).unwrap();
+/// let mut token = Token::new(String::from("#nlp"));
+/// action.apply(&mut token);  // token.value is now "<HASHTAG>"
+///
+/// assert!(Action::new(ActionKind::Emojize, TokenCondition::Hashtag).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
 pub struct Action {
-    pub action_name: Option<String>,
-    pub action_condition: String,
+    kind: ActionKind,
+    condition: TokenCondition,
+    /// The segmenter of the "segment" action, the built-in English one by default.
+    segmenter: Arc<Segmenter>,
 }
 
 impl Action{
-    pub fn remove(&self, token: &mut Token) -> () {
+    /// Create an action, failing if `kind` is not allowed for `condition`.
+    pub fn new(kind: ActionKind, condition: TokenCondition) -> Result<Action, ActionError> {
+        if !condition.allowed_actions().contains(&kind) {
+            return Err(ActionError::NotAllowed { action: kind, condition })
+        }
+        Ok(Action { kind, condition, segmenter: DEFAULT_SEGMENTER.clone() })
+    }
+
+    /// Set the segmenter of the "segment" action, e.g. `PrepConfig::segmenter`.
+    pub fn with_segmenter(mut self, segmenter: Arc<Segmenter>) -> Action {
+        self.segmenter = segmenter;
+        self
+    }
+
+    /// Create an action from its names, e.g. `Action::parse("tag", "is_hashtag")`.
+    pub fn parse(kind: &str, condition: &str) -> Result<Action, ActionError> {
+        Action::new(kind.parse()?, condition.parse()?)
+    }
+
+    pub fn kind(&self) -> ActionKind {
+        self.kind
+    }
+
+    pub fn condition(&self) -> TokenCondition {
+        self.condition
+    }
+
+    pub fn segmenter(&self) -> &Segmenter {
+        &self.segmenter
+    }
+
+    pub fn remove(&self, token: &mut Token) {
         token.set_value("".to_string())
     }
 
-    pub fn tag(&self, token: &mut Token) -> () {
-        token.set_value(match REPLACE_MAPPINGS.get(&self.action_condition as &str) {
-            Some(tag) => tag.to_string(),
-            None => token.value.to_string()
-        })
+    pub fn tag(&self, token: &mut Token) {
+        if let Some(tag) = self.condition.tag() {
+            token.set_value(tag.to_string())
+        }
     }
 
-    pub fn demojize(&self, token: &mut Token) -> () {
-        token.set_value(match emojis::get(&token.value) {
-            Some(demoji) => format!(":{}:", demoji.shortcode().unwrap_or(&token.value)),
-            _ => format!(":{}:", &token.value)
-        })
+    pub fn demojize(&self, token: &mut Token) {
+        token.set_value(demojized(&token.value))
     }
 
-    pub fn emojize(&self, token: &mut Token) -> () {
-        if (token.value.len() <= 2) {
-            // expect to have at least two ":"s, e.g., ":joy:"
-            token.value.to_string();
-        } else {
-            token.set_value(match emojis::get_by_shortcode(&token.value[1..token.value.len()-1]) {
-                Some(_emoji) => _emoji.to_string(),
-                _ => token.value.to_string(),
-            })
+    pub fn emojize(&self, token: &mut Token) {
+        if let Some(value) = emojized(&token.value) {
+            token.set_value(value)
         }
     }
 
-    pub fn is_action_valid(&self) -> bool {
-        if let Some(action_name) = &self.action_name {
-            if action_name.len() == 0 {
-                return false
-            }
-            if let Some(actions) = ACTION_MAPPING.get(&self.action_condition as &str) {
-                if !actions.contains(&&action_name.as_str()) {
-                    panic!(
-                        r#"Unknown action {action_name}, expected {expected_actions}"#,
-                        action_name=self.action_name.as_deref().unwrap_or_default(), expected_actions=
-                        ACTION_MAPPING.get(&self.action_condition as &str).unwrap().join(",")
-                    );
-                } else {
-                    return true
-                }
-            }
+    /// Segment the hashtag with the segmenter of the action.
+    pub fn segment(&self, token: &mut Token) {
+        self.segment_with(token, &self.segmenter, None)
+    }
+
+    /// Segment the hashtag with `segmenter`, `raw` being the hashtag in the raw text if known,
+    /// which keeps the camel case lost by the lowercasing.
+    pub fn segment_with(&self, token: &mut Token, segmenter: &Segmenter, raw: Option<&str>) {
+        token.set_value(segmenter.segment_hashtag(&token.value, raw))
+    }
+
+    /// The value of the token once the action is applied, None if the action leaves it unchanged.
+    fn new_value(&self, token: &Token, raw: Option<&str>) -> Option<String> {
+        match self.kind {
+            ActionKind::Remove => Some(String::new()),
+            ActionKind::Tag => self.condition.tag().map(str::to_string),
+            ActionKind::Demojize => Some(demojized(&token.value)),
+            ActionKind::Emojize => emojized(&token.value),
+            ActionKind::Segment => Some(self.segmenter.segment_hashtag(&token.value, raw)),
         }
-        false
     }
 
+    /// Apply the action if the token matches the condition, return whether it was applied.
+    ///
+    /// A matching error when classifying the new value gives `TokenKind::Other`, use `try_apply`
+    /// to get it instead.
     pub fn apply(&self, token: &mut Token) -> bool {
-        if !self.is_action_valid() {
+        if !self.condition.matches(token) {
             return false
         }
-        let is_condition_matched = match self.action_condition.as_str() {
-            "is_mention" => token.is_mention(),
-            "is_hashtag" => token.is_hashtag(),
-            "is_url" => token.is_url(),
-            "is_digit" => token.is_digit(),
-            "is_emoji" => token.is_emoji(),
-            "is_emoticon" => token.is_emoticon(),
-            "is_punct" => token.is_punct(),
-            "is_email" => token.is_email(),
-            "is_html_tag" => token.is_html_tag(),
-            &_ => false
-        };
-        if !is_condition_matched {
-            return false
+        if let Some(value) = self.new_value(token, None) {
+            token.set_value(value)
         }
-        match self.action_name.as_deref() {
-            Some("remove") => self.remove(token),
-            Some("tag") => self.tag(token),
-            Some("demojize") => self.demojize(token),
-            Some("emojize") => self.emojize(token),
-            _ => return false,
+        true
+    }
+
+    /// Apply the action like `apply`, failing on a matching error when classifying the new value.
+    pub fn try_apply(&self, token: &mut Token) -> Result<bool, PrepError> {
+        self.try_apply_with_raw(token, None)
+    }
+
+    /// Apply the action like `try_apply`, `raw` being the token in the raw text if known, which
+    /// keeps the camel case of a hashtag to segment lost by the lowercasing.
+    pub fn try_apply_with_raw(&self, token: &mut Token, raw: Option<&str>) -> Result<bool, PrepError> {
+        if !self.condition.matches(token) {
+            return Ok(false)
         }
-        return true
+        if let Some(value) = self.new_value(token, raw) {
+            token.try_set_value(value)?
+        }
+        Ok(true)
+    }
+}
+
+/// The textual representation of an emoji, e.g. ":joy:".
+fn demojized(value: &str) -> String {
+    match emojis::get(value) {
+        Some(demoji) => format!(":{}:", demoji.shortcode().unwrap_or(value)),
+        _ => format!(":{}:", value)
+    }
+}
+
+/// The emoji of a shortcode like ":joy:", or the value if it is unknown, None if it is too short.
+fn emojized(value: &str) -> Option<String> {
+    // expect to have at least two ":"s, e.g., ":joy:"
+    if value.len() <= 2 {
+        return None
     }
+    Some(match emojis::get_by_shortcode(&value[1..value.len()-1]) {
+        Some(emoji) => emoji.to_string(),
+        _ => value.to_string(),
+    })
 }
 
 #[cfg(test)]
@@ -203,7 +628,7 @@ mod tests {
     #[case("#정국생일ᄎᄏ", true)]
     #[case("#123", false)]  // # a hashtag can't be just a seq of numbers
     fn test_is_hashtag(#[case] value: String, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(value);
         assert_eq!(expected, token.is_hashtag())
     }
 
@@ -212,7 +637,7 @@ mod tests {
     #[case("www.google.fr", true)] // # without leading http(s)
     #[case("http://t.co/skU8zM7Slh", true)]
     fn test_is_url(#[case] value: String, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(value);
         assert_eq!(expected, token.is_url())
     }
 
@@ -221,7 +646,7 @@ mod tests {
     #[case("@@", false)]  // # not valid mention
     #[case("tutu@gmail.com", false)]  // # email
     fn test_is_mention(#[case] value: String, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(value);
         assert_eq!(expected, token.is_mention())
     }
 
@@ -230,7 +655,7 @@ mod tests {
     #[case(":joy:", true)]  // demojized emoji ('joy' is in the emoji alias)
     #[case(":notemoji:", false)]
     fn test_is_emoji(#[case] value: String, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(value);
         assert_eq!(expected, token.is_emoji())
     }
 
@@ -241,7 +666,7 @@ mod tests {
     #[case("12/34", true)]  // fraction
     #[case("12abc", false)]  // combination of numbers and alphabets
     fn test_is_digit(#[case] value: &str, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(String::from(value));
         assert_eq!(expected, token.is_digit())
     }
 
@@ -253,7 +678,7 @@ mod tests {
     #[case("#nlp", false)]
     #[case("12", false)]  // the length of token is not 1
     fn test_is_punct(#[case] value: &str, #[case] expected: bool) {
-        let mut token = Token {value: String::from(value)};
+        let mut token = Token::new(String::from(value));
         assert_eq!(expected, token.is_punct())
     }
 
@@ -261,52 +686,76 @@ mod tests {
     #[case("tutu@gmail.com", true)]
     #[case("@tutu", false)] // mention
     fn test_is_email(#[case] value: &str, #[case] expected: bool) {
-        let mut token = Token {value: value.to_owned()};
+        let mut token = Token::new(value.to_owned());
         assert_eq!(expected, token.is_email())
     }
     #[rstest]
     #[case("<p>", true)]
     #[case("</p>", true)]
     #[case("</p", false)]
+    #[case("<RT>", false)]
     fn test_is_html_tag(#[case] value: &str, #[case] expected: bool) {
-        let mut token = Token {value: value.to_owned()};
+        let mut token = Token::new(value.to_owned());
         assert_eq!(expected, token.is_html_tag())
     }
 
+    #[rstest]
+    #[case("#nlp", TokenKind::Hashtag)]
+    #[case("#123", TokenKind::Other)]
+    #[case("$AAPL", TokenKind::Cashtag)]
+    #[case("$brk.a", TokenKind::Cashtag)]
+    #[case("$5", TokenKind::Other)]
+    #[case(":joy:", TokenKind::Emoji)]
+    #[case("...", TokenKind::Punct)]
+    #[case("word", TokenKind::Word)]
+    #[case("", TokenKind::Other)]
+    fn test_token_kind_classify(#[case] value: &str, #[case] expected: TokenKind) {
+        assert_eq!(TokenKind::classify(value).unwrap(), expected)
+    }
+
+    #[test]
+    fn test_token_set_value_updates_kind() {
+        let mut token = Token::new("@nlp".to_owned());
+        assert!(token.is_mention());
+        token.set_value("#nlp".to_owned());
+        assert!(!token.is_mention());
+        assert!(token.is_hashtag());
+    }
+
+    #[test]
+    fn test_emoticon_is_punct() {
+        assert!(Token::new(":)".to_owned()).is_emoticon());
+        assert!(Token::new(":)".to_owned()).is_punct());
+        assert!(!Token::new(r"\(^o^)/".to_owned()).is_punct());
+    }
+
     #[test]
     fn test_token_check_flag() {
-        let mut token = Token {value: "#hashtag".to_owned()};
-        assert!(token.check_flag(&HASHTAG_RE));
-        let mut token = Token {value: "not_hashtag".to_owned()};
-        assert!(!token.check_flag(&HASHTAG_RE))
+        let mut token = Token::new("#hashtag".to_owned());
+        assert!(token.check_flag(&HASHTAG_RE).unwrap());
+        let mut token = Token::new("not_hashtag".to_owned());
+        assert!(!token.check_flag(&HASHTAG_RE).unwrap())
     }
 
     #[test]
     fn test_token_do_action_remove() {
-        let mut token = Token{value: "#hashtag".to_owned()};
-        token.do_action(&Action{action_name: Some(String::from("remove")), action_condition: "is_hashtag".to_owned()});
+        let mut token = Token::new("#hashtag".to_owned());
+        token.do_action(&Action::new(ActionKind::Remove, TokenCondition::Hashtag).unwrap());
         assert_eq!(token.value, "")
     }
 
     #[test]
     fn test_token_do_action_tag() {
-        let mut token = Token{value: String::from("#hashtag")};
-        token.do_action(&Action{action_name: Some(String::from("tag")), action_condition: "is_hashtag".to_owned()});
+        let mut token = Token::new(String::from("#hashtag"));
+        token.do_action(&Action::new(ActionKind::Tag, TokenCondition::Hashtag).unwrap());
         assert_eq!(token.value, *HASHTAG_TAG)
     }
 
-    #[test]
-    fn test_token_do_action_none() {
-        let mut token = Token{value: "#hashtag".to_owned()};
-        token.do_action(&Action{action_name: None, action_condition: "is_hashtag".to_owned()});
-        assert_eq!(token.value, "#hashtag")
-    }
-
     #[test]
     fn test_action_remove() {
         // arguments are not important here
-        let action = Action{action_name: Some(String::from("unittest")), action_condition: "unitest".to_owned()};
-        let mut token = Token{value: "test".to_owned()};
+        let action = Action::new(ActionKind::Remove, TokenCondition::Emoji).unwrap();
+        let mut token = Token::new("test".to_owned());
         action.remove(&mut token);
         assert_eq!(token.value, "")
     }
@@ -314,8 +763,8 @@ mod tests {
     #[test]
     fn test_action_demojize() {
         // arguments are not important here
-        let action = Action{action_name: Some(String::from("unittest")), action_condition: "unitest".to_owned()};
-        let mut token = Token{value: "😀".to_owned()};
+        let action = Action::new(ActionKind::Demojize, TokenCondition::Emoji).unwrap();
+        let mut token = Token::new("😀".to_owned());
         action.demojize(&mut token);
         assert_eq!(token.value, ":grinning:")
     }
@@ -323,61 +772,124 @@ mod tests {
     #[test]
     fn test_action_emojize() {
         // arguments are not important here
-        let action = Action{action_name: Some(String::from("unittest")), action_condition: "unitest".to_owned()};
-        let mut token = Token{value: ":grinning:".to_owned()};
+        let action = Action::new(ActionKind::Emojize, TokenCondition::Emoji).unwrap();
+        let mut token = Token::new(":grinning:".to_owned());
         action.emojize(&mut token);
         assert_eq!(token.value, "😀")
     }
 
     #[test]
     fn test_action_tag() {
-        let action = Action{action_name: Some(String::from("tag")), action_condition: "is_emoji".to_owned()};
-        let mut token = Token{value: "😰".to_owned()};
+        let action = Action::new(ActionKind::Tag, TokenCondition::Emoji).unwrap();
+        let mut token = Token::new("😰".to_owned());
         action.tag(&mut token);
         assert_eq!(token.value, "<EMOJI>")
     }
 
     #[rstest]
-    #[case("", "is_hashtag", false)] // action name is empty
+    #[case("#DavidLynchFan", "David Lynch Fan")]
+    #[case("#climatechange", "climate change")]
+    #[case("#covid_19", "covid 19")]
+    fn test_action_segment(#[case] value: &str, #[case] expected: &str) {
+        let action = Action::new(ActionKind::Segment, TokenCondition::Hashtag).unwrap();
+        let mut token = Token::new(value.to_owned());
+        assert!(action.apply(&mut token));
+        assert_eq!(token.value, expected)
+    }
+
+    #[test]
+    fn test_action_segment_with_segmenter() {
+        let segmenter = Arc::new(Segmenter::new([("new", 10), ("york", 5), ("newyork", 1)]));
+        let action = Action::new(ActionKind::Segment, TokenCondition::Hashtag).unwrap().with_segmenter(segmenter);
+        let mut token = Token::new("#newyork".to_owned());
+        assert!(action.apply(&mut token));
+        assert_eq!(token.value, "new york");
+        // the camel case of the raw hashtag splits the unknown words
+        let mut token = Token::new("#newyorkbigapple".to_owned());
+        assert!(action.try_apply_with_raw(&mut token, Some("#NewYorkBigApple")).unwrap());
+        assert_eq!(token.value, "new york big apple");
+    }
+
+    #[rstest]
     #[case("remove", "is_hashtag", true)]
     #[case("tag", "is_hashtag", true)]
-    fn test_action_is_action_valid(#[case] action_name: &str, #[case] action_condition: &str, #[case] expected: bool) {
-        let action = Action{action_name: Some(String::from(action_name)), action_condition: action_condition.to_owned()};
-        assert_eq!(action.is_action_valid(), expected)
+    #[case("emojize", "is_emoji", true)]
+    #[case("emojize", "is_hashtag", false)] // action is not allowed
+    #[case("tag", "is_html_tag", false)]
+    #[case("remove", "is_stop_word", true)]
+    #[case("tag", "is_stop_word", false)]
+    #[case("segment", "is_hashtag", true)]
+    #[case("segment", "is_emoji", false)]
+    #[case("", "is_hashtag", false)] // action name is empty
+    #[case("tagg", "is_hashtag", false)]
+    #[case("tag", "is_hashtags", false)]
+    fn test_action_parse(#[case] action_name: &str, #[case] action_condition: &str, #[case] expected: bool) {
+        assert_eq!(Action::parse(action_name, action_condition).is_ok(), expected)
     }
 
-    #[test]
-    fn test_action_is_action_valid_with_none_action_name() {
-        let action = Action{action_name: None, action_condition: "is_hashtag".to_owned()};
-        assert_eq!(action.is_action_valid(), false)
+    #[rstest]
+    #[case("tagg", "is_hashtag", r#"Unknown action "tagg", expected one of remove, tag, demojize, emojize, segment"#)]
+    #[case("tag", "is_hashtags", r#"Unknown condition "is_hashtags", expected one of is_mention, is_hashtag, is_cashtag, is_url, is_digit, is_emoji, is_emoticon, is_punct, is_email, is_html_tag, is_stop_word"#)]
+    #[case("emojize", "is_hashtag", r#"Action "emojize" is not allowed for is_hashtag, expected one of remove, tag, segment"#)]
+    #[case("segment", "is_mention", r#"Action "segment" is not allowed for is_mention, expected one of remove, tag"#)]
+    fn test_action_parse_error_message(#[case] action_name: &str, #[case] action_condition: &str, #[case] expected: &str) {
+        assert_eq!(Action::parse(action_name, action_condition).unwrap_err().to_string(), expected)
     }
 
     #[test]
-    #[should_panic]
-    fn test_action_is_action_valid_with_action_is_not_allowed() {
-        let action = Action{action_name: Some("emojize".to_owned()), action_condition: "is_hashtag".to_owned()};
-        assert_eq!(action.is_action_valid(), false)
+    fn test_action_kind_from_str() {
+        for kind in ActionKind::ALL {
+            assert_eq!(kind.as_str().parse::<ActionKind>(), Ok(kind));
+        }
+        for condition in TokenCondition::ALL {
+            assert_eq!(condition.as_str().parse::<TokenCondition>(), Ok(condition));
+        }
     }
 
     #[test]
     fn test_action_apply_returning_true() {
-        let action = Action{action_name: Some("remove".to_owned()), action_condition: "is_hashtag".to_owned()};
-        let mut token = Token{value: "#hashtag".to_owned()};
-        assert_eq!(action.apply(&mut token), true)
+        let action = Action::new(ActionKind::Remove, TokenCondition::Hashtag).unwrap();
+        let mut token = Token::new("#hashtag".to_owned());
+        assert!(action.apply(&mut token))
     }
 
     #[test]
     fn test_action_apply_returning_false() {
-        let action = Action{action_name: Some("remove".to_owned()), action_condition: "is_hashtag".to_owned()};
-        let mut token = Token{value: String::from("@hashtag")};
-        assert_eq!(action.apply(&mut token), false)
+        let action = Action::new(ActionKind::Remove, TokenCondition::Hashtag).unwrap();
+        let mut token = Token::new(String::from("@hashtag"));
+        assert!(!action.apply(&mut token))
+    }
+
+    #[test]
+    fn test_action_try_apply() {
+        let action = Action::new(ActionKind::Tag, TokenCondition::Mention).unwrap();
+        let mut token = Token::new(String::from("@nlp"));
+        assert!(action.try_apply(&mut token).unwrap());
+        assert_eq!(token.value, *MENTION_TAG);
+        assert!(!action.try_apply(&mut token).unwrap());
+        let action = Action::new(ActionKind::Demojize, TokenCondition::Emoji).unwrap();
+        let mut token = Token::try_new(String::from("😂")).unwrap();
+        assert!(action.try_apply(&mut token).unwrap());
+        assert_eq!((token.value.as_str(), token.kind), (":joy:", TokenKind::Emoji));
+    }
+
+    #[test]
+    fn test_try_set_value() {
+        let mut token = Token::try_new(String::from("hello")).unwrap();
+        assert_eq!(token.kind, TokenKind::Word);
+        token.try_set_value(String::from("#hello")).unwrap();
+        assert_eq!(token, Token::new(String::from("#hello")));
+        assert_eq!(token.kind, TokenKind::Hashtag);
     }
 
     #[rstest]
     #[case(":)", true)]
     #[case("(◕‿◕✿)", true)]
+    #[case("(•‿•)", true)]
+    #[case("(^‿^)", true)]
+    #[case("(a_b)", false)]
     fn test_is_emoticon(#[case] value: &str, #[case] expected: bool) {
-        let mut token = Token {value: value.to_owned()};
+        let mut token = Token::new(value.to_owned());
         assert_eq!(expected, token.is_emoticon())
     }
 }
\ No newline at end of file
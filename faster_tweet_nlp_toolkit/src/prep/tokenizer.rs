@@ -1,43 +1,426 @@
-use crate::prep::token::Token;
-use crate::prep::regexes::TWEET_TOKENIZE;
+use std::fmt;
 
+use pcre2::bytes::Regex;
+
+use crate::error::PrepError;
+use crate::prep::token::{Span, Token, TokenKind};
+use crate::prep::regexes::{REGEX_BUILDER, TWEET_TOKENIZE, _TOKEN_PIPELINE};
+
+/// Split a text into tokens.
+///
+/// `TweetTokenizer` is the default one, `WhitespaceTokenizer` and `RegexTokenizer` are the other
+/// built-in ones. Any `Fn(String) -> Vec<Token>`, e.g. a closure capturing a vocabulary, is a
+/// tokenizer too.
+///
+/// A tokenizer should give the span of each token in the text, otherwise the tokens are looked
+/// for in order in the text to know where they come from.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::config::PrepConfig;
+/// use faster_tweet_nlp_toolkit::prep::token::Token;
+/// use faster_tweet_nlp_toolkit::prep::text_prep::prep_with_config;
+/// let vocabulary = vec!["hello"];
+/// let config = PrepConfig::builder()
+///     .tokenizer(move |text: String| text.split(' ').filter(|x| vocabulary.contains(x)).map(|x| Token::new(x.to_string())).collect())
+///     .build()
+///     .unwrap();
+/// let result = prep_with_config(String::from("Hello world"), &config).unwrap();  // expect "hello"
+/// ```
+pub trait Tokenizer: Send + Sync {
+    /// Tokenize the text.
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError>;
+}
+
+impl<F> Tokenizer for F
+where
+    F: Fn(String) -> Vec<Token> + Send + Sync,
+{
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError> {
+        Ok(self(text.to_string()))
+    }
+}
+
+impl fmt::Debug for dyn Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tokenizer")
+    }
+}
+
+/// Count the chars up to the byte ranges of the tokens, which must come in order.
+#[derive(Default)]
+struct SpanCounter {
+    end: usize,
+    char_end: usize,
+}
+
+impl SpanCounter {
+    fn span(&mut self, text: &str, start: usize, end: usize) -> Span {
+        let char_start = self.char_end + text[self.end..start].chars().count();
+        let char_end = char_start + text[start..end].chars().count();
+        (self.end, self.char_end) = (end, char_end);
+        Span { start, end, char_start, char_end }
+    }
+}
+
+/// The tokenizer of the tweets with `TWEET_TOKENIZE`, the kind of each token being given by the
+/// pattern which matched it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TweetTokenizer;
+
+impl Tokenizer for TweetTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError> {
+        tokenize_pipeline(&TWEET_TOKENIZE, text)
+    }
+}
+
+/// Tokenize with a regex made of named groups, the kind of each token being given by the group which matched it.
+fn tokenize_pipeline(regex: &Regex, text: &str) -> Result<Vec<Token>, PrepError> {
+    let group_names = regex.capture_names();
+    let mut counter = SpanCounter::default();
+    regex.captures_iter(text.as_bytes()).map(|caps| {
+        let caps = caps?;
+        let m = caps.get(0).unwrap();
+        let span = counter.span(text, m.start(), m.end());
+        let value = text[m.start()..m.end()].to_string();
+        let group = (1..caps.len())
+            .find(|&i| caps.get(i).is_some())
+            .and_then(|i| group_names[i].as_deref())
+            .unwrap_or_default();
+        let kind = TokenKind::from_pipeline_group(group, &value)?;
+        Ok(Token::with_kind(value, kind).with_span(span))
+    }).collect()
+}
+
+/// The named token patterns of a tokenizer, tried in order at each position of the text.
+///
+/// `TokenPatternSet::new` starts from the patterns of `TWEET_TOKENIZE`: url, email, mention,
+/// hashtag, cashtag, emoticon, html_tag, ascii_arrow, digit, ellipsis_dots, emoji_string, word and other.
+/// A token matched by one of them gets the kind of the same name, e.g. `TokenKind::Url`, a token
+/// matched by a pattern of another name gets its kind from its value.
+///
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::tokenizer::{TokenPatternSet, Tokenizer};
+/// let mut patterns = TokenPatternSet::new();
+/// patterns.remove("cashtag");
+/// let digit = patterns.position("digit").unwrap();
+/// patterns.insert(digit, "alnum", r"\b[a-z]+\d+\b");
+/// let tokenizer = patterns.build().unwrap();
+/// let tokens = tokenizer.tokenize("covid19 up 5% $aapl").unwrap();  // expect "covid19", "up", "5", "%", "$", "aapl"
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenPatternSet {
+    patterns: Vec<(String, String)>,
+}
+
+impl Default for TokenPatternSet {
+    fn default() -> TokenPatternSet {
+        TokenPatternSet::new()
+    }
+}
+
+impl TokenPatternSet {
+    /// Create the set of the default token patterns.
+    pub fn new() -> TokenPatternSet {
+        TokenPatternSet {
+            patterns: _TOKEN_PIPELINE.iter().map(|(name, pattern)| (name.to_string(), pattern.to_string())).collect(),
+        }
+    }
+
+    /// Create a set without any pattern.
+    pub fn empty() -> TokenPatternSet {
+        TokenPatternSet { patterns: Vec::new() }
+    }
+
+    /// The names of the patterns, in the order they are tried.
+    pub fn names(&self) -> Vec<&str> {
+        self.patterns.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// The priority of a pattern, 0 being tried first.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.patterns.iter().position(|(x, _)| x == name)
+    }
+
+    /// Insert a named pattern at a priority, 0 being tried first, replacing the pattern of the same name if any.
+    ///
+    /// A priority beyond the number of patterns adds the pattern last.
+    pub fn insert(&mut self, priority: usize, name: &str, pattern: &str) -> &mut TokenPatternSet {
+        self.remove(name);
+        let priority = priority.min(self.patterns.len());
+        self.patterns.insert(priority, (name.to_string(), pattern.to_string()));
+        self
+    }
+
+    /// Add a named pattern, tried last.
+    pub fn push(&mut self, name: &str, pattern: &str) -> &mut TokenPatternSet {
+        self.insert(usize::MAX, name, pattern)
+    }
+
+    /// Remove a pattern, nothing happens if there is none of this name.
+    pub fn remove(&mut self, name: &str) -> &mut TokenPatternSet {
+        self.patterns.retain(|(x, _)| x != name);
+        self
+    }
+
+    /// Move a pattern to another priority, nothing happens if there is none of this name.
+    pub fn move_to(&mut self, name: &str, priority: usize) -> &mut TokenPatternSet {
+        if let Some(i) = self.position(name) {
+            let (name, pattern) = self.patterns.remove(i);
+            self.patterns.insert(priority.min(self.patterns.len()), (name, pattern));
+        }
+        self
+    }
+
+    /// Compile the patterns into a tokenizer, failing on an invalid pattern or name.
+    pub fn build(&self) -> Result<PipelineTokenizer, PrepError> {
+        let pipeline = self.patterns.iter()
+            .map(|(name, pattern)| format!(r#"(?<{}>{})"#, name, pattern))
+            .collect::<Vec<_>>()
+            .join(r"|");
+        Ok(PipelineTokenizer { regex: REGEX_BUILDER.lock().unwrap().build(&pipeline)? })
+    }
+}
+
+/// A tokenizer compiled from a `TokenPatternSet`.
+#[derive(Clone, Debug)]
+pub struct PipelineTokenizer {
+    regex: Regex,
+}
+
+impl PipelineTokenizer {
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+}
+
+impl Tokenizer for PipelineTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError> {
+        tokenize_pipeline(&self.regex, text)
+    }
+}
+
+/// Tokenize the text with `TWEET_TOKENIZE`, the kind of each token being given by the pattern which matched it
+/// and its span by the position of the match in the text.
+///
+/// # Panics
+///
+/// Panics on a matching error, use `TweetTokenizer` to get it as a `PrepError` instead.
 pub fn tweet_tokenize(text: String) -> Vec<Token> {
-    unsafe {
-        TWEET_TOKENIZE.find_iter(&text.as_bytes()).map(
-            |m| Token::new(String::from_utf8_unchecked(m.unwrap().subject().to_vec()))).collect()
+    TweetTokenizer.tokenize(&text).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Split the text on whitespaces.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError> {
+        let mut counter = SpanCounter::default();
+        text.split_whitespace().map(|value| {
+            let start = value.as_ptr() as usize - text.as_ptr() as usize;
+            Ok(Token::try_new(value.to_string())?.with_span(counter.span(text, start, start + value.len())))
+        }).collect()
+    }
+}
+
+/// Take every match of a regex as a token, e.g. `\w+|[^\w\s]+`.
+#[derive(Clone, Debug)]
+pub struct RegexTokenizer {
+    regex: Regex,
+}
+
+impl RegexTokenizer {
+    /// Create a tokenizer from the pattern of the tokens, with Unicode properties like the pipeline regexes.
+    pub fn new(pattern: &str) -> Result<RegexTokenizer, PrepError> {
+        Ok(RegexTokenizer::from_regex(REGEX_BUILDER.lock().unwrap().build(pattern)?))
+    }
+
+    /// Create a tokenizer from an already compiled regex, e.g. built with a `RegexBuilder`.
+    pub fn from_regex(regex: Regex) -> RegexTokenizer {
+        RegexTokenizer { regex }
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+}
+
+impl Tokenizer for RegexTokenizer {
+    fn tokenize(&self, text: &str) -> Result<Vec<Token>, PrepError> {
+        let mut counter = SpanCounter::default();
+        self.regex.find_iter(text.as_bytes())
+            .filter(|m| !matches!(m, Ok(m) if m.start() == m.end()))
+            .map(|m| {
+                let m = m?;
+                let value = text[m.start()..m.end()].to_string();
+                Ok(Token::try_new(value)?.with_span(counter.span(text, m.start(), m.end())))
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
+    fn _tokenize_without_spans(text: &str) -> Vec<Token> {
+        tweet_tokenize(text.to_string()).into_iter().map(|token| Token { span: None, ..token }).collect()
+    }
+
     #[test]
     fn test_tweet_tokenize() {
         let token_values = vec!["@remy", ":", "This", "is", "waaaaayyyy", "#too", "much", "for", "you"];
-        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token{value: x.to_owned()}).collect();
+        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token::new(x.to_owned())).collect();
         itertools::assert_equal(
-            tweet_tokenize(" @remy: This is waaaaayyyy #too much for you".to_string()),
+            _tokenize_without_spans(" @remy: This is waaaaayyyy #too much for you"),
             expected_tokens
         );
         let token_values = vec!["คลับพาราไดซ์", ",", "จะถูกต้อง", ".", "วันสุดท้ายทุกสิ่งที่ดูเหมือนว่าตกลง"];
-        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token{value: x.to_owned()}).collect();
+        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token::new(x.to_owned())).collect();
         itertools::assert_equal(
-            tweet_tokenize(" คลับพาราไดซ์, จะถูกต้อง. วันสุดท้ายทุกสิ่งที่ดูเหมือนว่าตกลง".to_string()),
+            _tokenize_without_spans(" คลับพาราไดซ์, จะถูกต้อง. วันสุดท้ายทุกสิ่งที่ดูเหมือนว่าตกลง"),
             expected_tokens
         );
 
         let token_values = vec!["http://t.co/skU8zM7Slh", ":joy:"];
-        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token{value: x.to_owned()}).collect();
+        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token::new(x.to_owned())).collect();
         itertools::assert_equal(
-            tweet_tokenize("http://t.co/skU8zM7Slh :joy:".to_string()),
+            _tokenize_without_spans("http://t.co/skU8zM7Slh :joy:"),
             expected_tokens
         );
         let token_values = vec!["123", "@hello", "#world", r"\(^o^)/", "www.url.com", ":)", "abc@gmail.com"];
-        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token{value: x.to_owned()}).collect();
+        let expected_tokens: Vec<Token> = token_values.into_iter().map(|x| Token::new(x.to_owned())).collect();
         itertools::assert_equal(
-            tweet_tokenize(r"123 @hello #world \(^o^)/ www.url.com :) abc@gmail.com".to_string()),
+            _tokenize_without_spans(r"123 @hello #world \(^o^)/ www.url.com :) abc@gmail.com"),
             expected_tokens
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tweet_tokenize_kinds() {
+        let kinds: Vec<TokenKind> = tweet_tokenize(
+            r"123 @hello #world #2019 \(^o^)/ www.url.com :) abc@gmail.com <p> word 😰 :joy: !".to_string()
+        ).into_iter().map(|token| token.kind).collect();
+        assert_eq!(kinds, vec![
+            TokenKind::Digit,
+            TokenKind::Mention,
+            TokenKind::Hashtag,
+            TokenKind::Other,
+            TokenKind::Emoticon,
+            TokenKind::Url,
+            TokenKind::Emoticon,
+            TokenKind::Email,
+            TokenKind::HtmlTag,
+            TokenKind::Word,
+            TokenKind::Emoji,
+            TokenKind::Emoji,
+            TokenKind::Punct,
+        ]);
+    }
+
+    #[test]
+    fn test_tweet_tokenize_spans() {
+        let spans: Vec<Span> = tweet_tokenize("été @remy 😰!".to_string()).into_iter().map(|token| token.span.unwrap()).collect();
+        assert_eq!(spans, vec![
+            Span { start: 0, end: 5, char_start: 0, char_end: 3 },
+            Span { start: 6, end: 11, char_start: 4, char_end: 9 },
+            Span { start: 12, end: 16, char_start: 10, char_end: 11 },
+            Span { start: 16, end: 17, char_start: 11, char_end: 12 },
+        ]);
+    }
+
+    #[test]
+    fn test_whitespace_tokenizer() {
+        let tokens = WhitespaceTokenizer.tokenize(" été  @remy\t:)").unwrap();
+        let tokens: Vec<(&str, TokenKind, usize, usize)> = tokens.iter()
+            .map(|token| (token.value.as_str(), token.kind, token.span.unwrap().char_start, token.span.unwrap().char_end))
+            .collect();
+        assert_eq!(tokens, vec![
+            ("été", TokenKind::Word, 1, 4),
+            ("@remy", TokenKind::Mention, 6, 11),
+            (":)", TokenKind::Emoticon, 12, 14),
+        ]);
+    }
+
+    #[rstest]
+    #[case(r"\w+", "c'est l'été", vec!["c", "est", "l", "été"])]
+    #[case(r"[^,]*", "a,b,,c", vec!["a", "b", "c"])]
+    fn test_regex_tokenizer(#[case] pattern: &str, #[case] text: &str, #[case] expected: Vec<&str>) {
+        let tokenizer = RegexTokenizer::new(pattern).unwrap();
+        let tokens = tokenizer.tokenize(text).unwrap();
+        assert_eq!(tokens.iter().map(|token| token.value.as_str()).collect::<Vec<_>>(), expected);
+        assert!(tokens.iter().all(|token| text[token.span.unwrap().start..token.span.unwrap().end] == token.value));
+    }
+
+    #[test]
+    fn test_regex_tokenizer_with_invalid_pattern() {
+        assert!(matches!(RegexTokenizer::new("(a"), Err(PrepError::Regex(_))));
+    }
+
+    #[test]
+    fn test_closure_tokenizer() {
+        let suffix = String::from("!");
+        let tokenizer = move |text: String| vec![Token::new(text + &suffix)];
+        assert_eq!(tokenizer.tokenize("a").unwrap(), vec![Token::new(String::from("a!"))]);
+    }
+
+    #[test]
+    fn test_token_pattern_set() {
+        let set = TokenPatternSet::new();
+        assert_eq!(set.names(), vec![
+            "url", "email", "mention", "hashtag", "cashtag", "emoticon", "html_tag", "ascii_arrow", "digit",
+            "ellipsis_dots", "emoji_string", "word", "other",
+        ]);
+        let text = "@remy: $AAPL up 5% :) www.url.com";
+        assert_eq!(set.build().unwrap().tokenize(text).unwrap(), TweetTokenizer.tokenize(text).unwrap());
+
+        let tokenizer = TokenPatternSet::new()
+            .remove("cashtag")
+            .insert(0, "ticker", r"\$[A-Z]+")
+            .remove("emoticon")
+            .remove("does_not_exist")
+            .build()
+            .unwrap();
+        let tokens: Vec<(String, TokenKind)> = tokenizer.tokenize(text).unwrap().into_iter().map(|token| (token.value, token.kind)).collect();
+        assert_eq!(tokens, vec![
+            ("@remy".to_string(), TokenKind::Mention),
+            (":".to_string(), TokenKind::Punct),
+            ("$AAPL".to_string(), TokenKind::Other),
+            ("up".to_string(), TokenKind::Word),
+            ("5".to_string(), TokenKind::Digit),
+            ("%".to_string(), TokenKind::Punct),
+            (":".to_string(), TokenKind::Punct),
+            (")".to_string(), TokenKind::Punct),
+            ("www.url.com".to_string(), TokenKind::Url),
+        ]);
+    }
+
+    #[test]
+    fn test_token_pattern_set_reorder() {
+        let mut set = TokenPatternSet::empty();
+        set.push("digit", r"\d+").push("word", r"\w+").push("other", r"\S");
+        assert_eq!(set.build().unwrap().tokenize("abc123").unwrap().len(), 1);
+        set.move_to("digit", 1).insert(0, "word", r"[^\W\d]+");
+        assert_eq!(set.names(), vec!["word", "digit", "other"]);
+        let values: Vec<String> = set.build().unwrap().tokenize("abc123").unwrap().into_iter().map(|token| token.value).collect();
+        assert_eq!(values, vec!["abc", "123"]);
+        assert!(matches!(set.push("bad name", "x").build(), Err(PrepError::Regex(_))));
+    }
+
+    #[rstest]
+    #[case("$AAPL and $btc, $BRK.B!", vec!["$AAPL", "$btc", "$BRK.B"])]
+    #[case("$5 US$AAPL $TOOLONGX $x", vec!["$x"])]
+    fn test_tweet_tokenize_cashtags(#[case] text: &str, #[case] expected: Vec<&str>) {
+        let cashtags: Vec<String> = tweet_tokenize(text.to_string()).into_iter()
+            .filter(|token| token.is_cashtag())
+            .map(|token| token.value)
+            .collect();
+        assert_eq!(cashtags, expected);
+    }
+}
@@ -1,10 +1,19 @@
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 
 use pcre2::bytes::Regex;
 use unicode_normalization::UnicodeNormalization;
 use unicode_categories::UnicodeCategories;
 use lazy_static::lazy_static;
 use crate::constants::VARIATION_SELECTORS;
+use crate::error::PrepError;
+
+lazy_static! {
+    /// A char glued to the scheme of a URL, see `preprocess_url`.
+    pub(crate) static ref HTTP_RE: Regex = Regex::new(r#"([^ ])(https?://)"#).unwrap();
+}
 
 /// Strip the accents
 /// # Example
@@ -13,7 +22,7 @@ use crate::constants::VARIATION_SELECTORS;
 /// use faster_tweet_nlp_toolkit::prep::utils::strip_accents_unicode;
 /// let result = strip_accents_unicode("être");  // expect "etre"
 /// ```
-pub fn strip_accents_unicode(text: &str) -> Cow<String> {
+pub fn strip_accents_unicode(text: &str) -> Cow<'_, String> {
     let normlized_text = UnicodeNormalization::nfd(text).collect::<String>();
     let mut output: String = String::with_capacity(text.len());
     for ch in normlized_text.chars(){
@@ -21,7 +30,7 @@ pub fn strip_accents_unicode(text: &str) -> Cow<String> {
             output.push(ch);
         }
     }
-    return Cow::Owned(output)
+    Cow::Owned(output)
 }
 
 /// Remove the variation selectors
@@ -36,7 +45,7 @@ pub fn remove_variation_selectors(text: &str) -> String {
     for var in VARIATION_SELECTORS.iter().collect::<Vec<_>>(){
         t = t.replace(var, "");
     }
-    return t
+    t
 }
 
 /// Preprocess the URL so that the text appearing before the URL gets split from the URL.
@@ -47,17 +56,107 @@ pub fn remove_variation_selectors(text: &str) -> String {
 /// let result = preprocess_url(":http://url");  // expect ": http://url"
 /// ```
 pub fn preprocess_url(text: &str) -> String {
-    lazy_static! {
-        static ref HTTP_RE: Regex = Regex::new(r#"([^ ])(https?://)"#).unwrap();
-    }
     let pattern: &Regex = &HTTP_RE;
-    let t = String::from_utf8(pattern.replace_all(text.as_bytes(), "$1 $2".as_bytes()).to_vec()).unwrap();
-    return t
+    String::from_utf8(pattern.replace_all(text.as_bytes(), "$1 $2".as_bytes()).to_vec()).unwrap()
+}
+
+/// Read a list of words, one per line, skipping the blank lines.
+/// # Example
+///
+/// ```no_run
+/// use faster_tweet_nlp_toolkit::prep::utils::read_word_list;
+/// let words = read_word_list("words.txt").unwrap();
+/// ```
+pub fn read_word_list(path: &str) -> Result<HashSet<String>, PrepError> {
+    let contents = fs::read_to_string(path).map_err(|err| PrepError::io(path, err))?;
+    Ok(contents.lines().map(str::trim).filter(|word| !word.is_empty()).map(String::from).collect())
+}
+
+/// Give `text` the case of `word`: uppercase if the word is, capitalized if it starts with an
+/// uppercase letter, else as it is.
+/// # Example
+///
+/// ```
+/// use faster_tweet_nlp_toolkit::prep::utils::match_case;
+/// let result = match_case("IDK", "i don't know");  // expect "I DON'T KNOW"
+/// ```
+pub fn match_case(word: &str, text: &str) -> String {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    match letters.next() {
+        Some(first) if first.is_uppercase() => {
+            if word.chars().filter(|c| c.is_alphabetic()).count() > 1 && letters.all(char::is_uppercase) {
+                text.to_uppercase()
+            } else {
+                let mut chars = text.chars();
+                chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Parse the `(word, expansion)` pairs of a data file: a JSON object if `path` ends with `.json`,
+/// else one word and its expansion per line, separated by a tab, skipping the blank lines.
+pub(crate) fn parse_expansions(path: &str, contents: &str) -> Result<Vec<(String, String)>, PrepError> {
+    let error = |line: usize, message: String| PrepError::Parse { path: path.to_string(), line, message };
+    if Path::new(path).extension().is_some_and(|extension| extension == "json") {
+        let expansions: HashMap<String, String> = serde_json::from_str(contents).map_err(|err| error(err.line(), err.to_string()))?;
+        return Ok(expansions.into_iter().collect());
+    }
+    let mut expansions = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.split_once('\t') {
+            Some((word, expansion)) if !word.trim().is_empty() && !expansion.trim().is_empty() => {
+                expansions.push((word.trim().to_string(), expansion.trim().to_string()));
+            }
+            _ => return Err(error(i + 1, "expected a word and its expansion separated by a tab".to_string())),
+        }
+    }
+    Ok(expansions)
+}
+
+/// Implement `ALL`, `as_str`, `Display` and `FromStr` for a fieldless enum named by strings, e.g.
+/// `named_enum!(RetweetMode, "retweet mode", { Keep => "keep", Strip => "strip", Tag => "tag" })`,
+/// an unknown name giving `Unknown retweet mode "x", expected one of keep, strip, tag`.
+macro_rules! named_enum {
+    ($name:ident, $what:literal, { $($variant:ident => $str:literal),+ $(,)? }) => {
+        impl $name {
+            pub const ALL: [$name; [$($str),+].len()] = [$($name::$variant),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $str,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<$name, String> {
+                $name::ALL.into_iter().find(|value| value.as_str() == s).ok_or_else(|| {
+                    let names: Vec<&str> = $name::ALL.iter().map($name::as_str).collect();
+                    format!(r#"Unknown {} "{}", expected one of {}"#, $what, s, names.join(", "))
+                })
+            }
+        }
+    };
 }
+pub(crate) use named_enum;
 
 #[cfg(test)]
 mod tests {
     use crate::prep::utils::*;
+    use crate::test_dir::TestDir;
 
     #[test]
     fn test_strip_accents_unicode() {
@@ -68,4 +167,33 @@ mod tests {
     fn test_preprocess_url() {
         assert_eq!(preprocess_url(":http://t.co/skU8zM7Slh"), ": http://t.co/skU8zM7Slh");
     }
+
+    #[test]
+    fn test_read_word_list() {
+        let dir = TestDir::new("read_word_list");
+        let path = dir.path("words.txt");
+        fs::write(&path, "so\n good \n\n").unwrap();
+        let words = read_word_list(&path).unwrap();
+        assert_eq!(words, HashSet::from(["so".to_string(), "good".to_string()]));
+        fs::remove_file(&path).unwrap();
+        assert!(read_word_list(&path).is_err());
+    }
+
+    #[test]
+    fn test_match_case() {
+        assert_eq!(match_case("IDK", "i don't know"), "I DON'T KNOW");
+        assert_eq!(match_case("Idk", "i don't know"), "I don't know");
+        assert_eq!(match_case("I", "i"), "I");
+        assert_eq!(match_case("idk", "I don't know"), "I don't know");
+    }
+
+    #[test]
+    fn test_parse_expansions() {
+        let expansions = parse_expansions("slang.json", r#"{"idk": "i don't know"}"#).unwrap();
+        assert_eq!(expansions, vec![("idk".to_string(), "i don't know".to_string())]);
+        let err = parse_expansions("slang.json", "{\n\"idk\": 1}").unwrap_err();
+        assert!(err.to_string().starts_with("slang.json:2: invalid type"), "{}", err);
+        let err = parse_expansions("slang.tsv", "idk\ti don't know\n\nlol laughing out loud\n").unwrap_err();
+        assert_eq!(err.to_string(), "slang.tsv:3: expected a word and its expansion separated by a tab");
+    }
 }
\ No newline at end of file
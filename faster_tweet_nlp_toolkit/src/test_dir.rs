@@ -0,0 +1,26 @@
+//! The temporary directories of the tests, shared by the library and the `ftnt` binary.
+use std::fs;
+use std::path::PathBuf;
+
+/// A temporary directory of a test, unique to the test process and removed when dropped.
+pub(crate) struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Create the directory `ftnt_test_{name}_{pid}`, `name` being unique among the tests.
+    pub(crate) fn new(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("ftnt_test_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+
+    /// The path of a file of the directory.
+    pub(crate) fn path(&self, file: &str) -> String {
+        self.0.join(file).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
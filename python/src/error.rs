@@ -0,0 +1,22 @@
+use std::io::ErrorKind;
+
+use ftnt::error::PrepError;
+use pyo3::exceptions::{PyFileNotFoundError, PyOSError, PyPermissionError, PyRuntimeError, PyValueError};
+use pyo3::PyErr;
+
+/// Convert a `PrepError` into the closest Python exception.
+pub(crate) fn to_py_err(err: PrepError) -> PyErr {
+    let message = err.to_string();
+    match err {
+        PrepError::Action(_) | PrepError::Utf8 { .. } | PrepError::Parse { .. } | PrepError::Serialize { .. } => {
+            PyValueError::new_err(message)
+        }
+        PrepError::Regex(_) | PrepError::Cancelled => PyRuntimeError::new_err(message),
+        PrepError::Io { source, .. } => match source.kind() {
+            ErrorKind::NotFound => PyFileNotFoundError::new_err(message),
+            ErrorKind::PermissionDenied => PyPermissionError::new_err(message),
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => PyValueError::new_err(message),
+            _ => PyOSError::new_err(message),
+        },
+    }
+}
@@ -1,7 +1,11 @@
+// pyo3 0.18 expands `#[pymethods]` into impl blocks nested in a const item.
+#![allow(non_local_definitions)]
 #[macro_use(concat_string)]
 extern crate concat_string;
 extern crate faster_tweet_nlp_toolkit as ftnt;
 use pyo3::prelude::*;
+mod error;
+mod preprocessor;
 mod token;
 mod text_parser;
 mod text_prep;
@@ -11,12 +15,20 @@ mod text_prep;
 /// import the module.
 #[pymodule]
 #[pyo3(name = "faster_tweet_nlp_toolkit")]
-fn faster_tweet_nlp_toolkit(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+fn faster_tweet_nlp_toolkit(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<token::PyToken>()?;
+    m.add_class::<token::PyAction>()?;
     m.add_class::<text_parser::PyParsedText>()?;
+    m.add_class::<preprocessor::PyPreprocessor>()?;
+    // `isinstance(parsed_text, collections.abc.MutableSequence)`
+    py.import("collections.abc")?.getattr("MutableSequence")?
+        .call_method1("register", (py.get_type::<text_parser::PyParsedText>(),))?;
     m.add_function(wrap_pyfunction!(text_parser::parse_text, m)?)?;
     m.add_function(wrap_pyfunction!(text_parser::preprocess_text, m)?)?;
     m.add_function(wrap_pyfunction!(text_prep::prep, m)?)?;
+    m.add_function(wrap_pyfunction!(text_prep::prep_batch, m)?)?;
     m.add_function(wrap_pyfunction!(text_prep::prep_file, m)?)?;
+    m.add_function(wrap_pyfunction!(text_parser::load_config, m)?)?;
+    m.add_function(wrap_pyfunction!(text_parser::save_config, m)?)?;
     Ok(())
 }
@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use ftnt::config::{PrepConfig, Typography};
+use ftnt::contractions::Contractions;
+use ftnt::file_format::{CsvColumn, CsvOptions, ExtraField, FileFormat, JsonlOptions};
+use ftnt::lexicon::Lexicon;
+use ftnt::options::{PrepOptions, TokenizerOptions};
+use ftnt::segmenter::Segmenter;
+use ftnt::slang::Slang;
+use ftnt::stopwords::StopWords;
+use ftnt::text_parser::parse_text_with_config as parse_text_rust;
+use ftnt::text_prep::prep_batch_with_config as prep_batch_rust;
+use ftnt::text_prep::prep_file_with_options as prep_file_rust;
+use ftnt::text_prep::prep_with_config as prep_rust;
+use ftnt::text_prep::FileOptions;
+use ftnt::PrepError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFrozenSet, PySet, PyString};
+
+use crate::error::to_py_err;
+use crate::text_parser::{parse_action, PyParsedText};
+
+/// A preprocessing pipeline, built once from its options and applied to many texts.
+///
+/// All the options are keyword-only, None keeping the value of `config`, or else the default:
+///
+/// * `config`: a preset, "bert-input", "topic-modeling", "sentiment" or "word-embeddings", or a
+///   TOML, JSON or YAML file of options, see `load_config`, default to None.
+/// * `encoding`: re-encode the text, e.g. "ascii", default to None.
+/// * `remove_unencodable_char`: remove the '�' left by an encoding error, default to False.
+/// * `typography`: the typographic variants replaced by their plain form, e.g. "don’t" by "don't",
+///   True or False for all or none of them, or some of "quotes", "apostrophes", "dashes",
+///   "ellipsis", "full_width" and "spaces", default to False.
+/// * `to_lower`: lowercase the text, default to True.
+/// * `strip_accents`: strip the accents, default to False.
+/// * `reduce_len`: reduce the repeated characters, default to False.
+/// * `max_repeat`: how many times `reduce_len` lets a character be repeated, 0 being taken as 1,
+///   default to 3.
+/// * `protect_tokens`: whether `reduce_len` leaves the urls, emails, mentions and digits as they
+///   are, default to True.
+/// * `vocabulary`: the words `reduce_len` reduces the lengthened words to, or the path of a file
+///   of words, one per line, default to None.
+/// * `retweet`: what to do with the `RT @user: ` prefix of a retweet, "keep", "strip" or "tag",
+///   default to "keep".
+/// * `contractions`: the contractions to expand, e.g. "can't" to "can not", the built-in "en" or
+///   "fr", the path of a file of `contraction<TAB>expansion` lines, a dict of expansions, or a list
+///   of them, the later ones overriding the earlier ones, default to None.
+/// * `tokenizer`: "tweet" or "whitespace", default to "tweet".
+/// * `token_pattern`: a regex matching the tokens, instead of `tokenizer`, default to None.
+/// * `filters`: the tokens to filter out, default to None.
+/// * `slang`: the slang and abbreviations to normalize after the tokenization, e.g. "idk" to "i do
+///   not know", the built-in "en", the path of a TSV or JSON file of expansions, a dict of
+///   expansions, or a list of them, the later ones overriding the earlier ones, default to None.
+///   The mentions, hashtags, urls and emails are never normalized.
+/// * `slang_mode`: "replace" the slang tokens by their expansion, "append" it to them, or only
+///   "annotate" them with `Token.expansion`, default to "replace".
+/// * `stopwords`: the stop words to remove, the built-in "en", "fr", "es", "de", "it" or "pt", the
+///   path of a file of words, one per line, a set of words, or a list of them, all merged, default
+///   to None.
+/// * `keep_negations`: keep the negations of the stop words, e.g. "not" or "never", default to False.
+/// * `segmenter`: the word counts the "segment" action splits the hashtags with, or the path of a
+///   file of `word count` lines, default to the built-in English unigrams.
+/// * `emojis`, `emoticons`, `mentions`, `hashtags`, `cashtags`, `urls`, `digits`, `puncts`,
+///   `emails`, `html_tags`: the action on these tokens, e.g. "remove" or "tag", default to None.
+#[pyclass(module = "faster_tweet_nlp_toolkit", name = "Preprocessor", text_signature = "(*, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None)")]
+#[derive(Clone, Debug)]
+pub struct PyPreprocessor {
+    pub(crate) config: PrepConfig,
+}
+
+#[pymethods]
+impl PyPreprocessor {
+    #[new]
+    #[pyo3(signature = (*, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        config: Option<&str>,
+        encoding: Option<&str>,
+        remove_unencodable_char: Option<bool>,
+        typography: Option<&PyAny>,
+        to_lower: Option<bool>,
+        strip_accents: Option<bool>,
+        reduce_len: Option<bool>,
+        max_repeat: Option<usize>,
+        protect_tokens: Option<bool>,
+        vocabulary: Option<&PyAny>,
+        retweet: Option<&str>,
+        contractions: Option<&PyAny>,
+        tokenizer: Option<&str>,
+        token_pattern: Option<&str>,
+        filters: Option<&PyAny>,
+        slang: Option<&PyAny>,
+        slang_mode: Option<&str>,
+        stopwords: Option<&PyAny>,
+        keep_negations: Option<bool>,
+        segmenter: Option<&PyAny>,
+        emojis: Option<&str>,
+        emoticons: Option<&str>,
+        mentions: Option<&str>,
+        hashtags: Option<&str>,
+        cashtags: Option<&str>,
+        urls: Option<&str>,
+        digits: Option<&str>,
+        puncts: Option<&str>,
+        emails: Option<&str>,
+        html_tags: Option<&str>,
+    ) -> PyResult<PyPreprocessor> {
+        let mut options = match config {
+            Some(config) => PrepOptions::from_preset_or_file(config).map_err(to_py_err)?,
+            None => PrepOptions::default(),
+        };
+        if let Some(encoding) = encoding {
+            options.encoding = Some(encoding.to_string());
+        }
+        if let Some(typography) = typography {
+            options.typography = extract_typography(typography)?;
+        }
+        for (value, option) in [
+            (remove_unencodable_char, &mut options.remove_unencodable_char),
+            (to_lower, &mut options.to_lower),
+            (strip_accents, &mut options.strip_accents),
+            (reduce_len, &mut options.reduce_len),
+            (protect_tokens, &mut options.protect_tokens),
+            (keep_negations, &mut options.keep_negations),
+        ] {
+            if let Some(value) = value {
+                *option = value;
+            }
+        }
+        options.max_repeat = max_repeat.unwrap_or(options.max_repeat);
+        if let Some(retweet) = retweet {
+            options.retweet = retweet.parse().map_err(PyValueError::new_err)?;
+        }
+        match (tokenizer, token_pattern) {
+            (_, Some(pattern)) => options.tokenizer = TokenizerOptions::Regex(pattern.to_string()),
+            (Some("tweet"), None) => options.tokenizer = TokenizerOptions::Tweet,
+            (Some("whitespace"), None) => options.tokenizer = TokenizerOptions::Whitespace,
+            (Some(other), None) => {
+                return Err(PyValueError::new_err(format!(r#"Unknown tokenizer "{}", expected one of tweet, whitespace"#, other)));
+            }
+            (None, None) => {}
+        }
+        if let Some(filters) = filters {
+            options.filters = extract_strings(filters)?;
+        }
+        if let Some(slang_mode) = slang_mode {
+            options.slang_mode = slang_mode.parse().map_err(PyValueError::new_err)?;
+        }
+        for (action, option) in [
+            (emojis, &mut options.emojis),
+            (emoticons, &mut options.emoticons),
+            (mentions, &mut options.mentions),
+            (hashtags, &mut options.hashtags),
+            (cashtags, &mut options.cashtags),
+            (urls, &mut options.urls),
+            (digits, &mut options.digits),
+            (puncts, &mut options.puncts),
+            (emails, &mut options.emails),
+            (html_tags, &mut options.html_tags),
+        ] {
+            if let Some(action) = action {
+                *option = Some(parse_action(action)?);
+            }
+        }
+        // a path goes through the options, words or counts are set on the config
+        let vocabulary = match vocabulary {
+            Some(path) if path.is_instance_of::<PyString>()? => {
+                options.vocabulary = Some(path.extract()?);
+                None
+            }
+            vocabulary => vocabulary.map(extract_strings).transpose()?,
+        };
+        let segmenter = match segmenter {
+            Some(path) if path.is_instance_of::<PyString>()? => {
+                options.segmenter = Some(path.extract()?);
+                None
+            }
+            Some(counts) => Some(extract_segmenter(counts)?),
+            None => None,
+        };
+        let contractions = contractions.map(extract_contractions).transpose()?;
+        let slang = slang.map(extract_slang).transpose()?;
+        let stopwords = stopwords.map(extract_stopwords).transpose()?;
+        let mut config = options.build().map_err(to_py_err)?;
+        if contractions.is_some() {
+            config.contractions = contractions;
+        }
+        if slang.is_some() {
+            config.slang = slang;
+        }
+        if stopwords.is_some() {
+            config.stopwords = stopwords;
+        }
+        if let Some(words) = vocabulary {
+            config.vocabulary = Some(Arc::new(words.into_iter().collect()));
+        }
+        if let Some(segmenter) = segmenter {
+            config.segmenter = Arc::new(segmenter);
+        }
+        Ok(PyPreprocessor { config })
+    }
+
+    /// Preprocess a text and return the cleaned text.
+    pub fn __call__(&self, py: Python<'_>, text: String) -> PyResult<String> {
+        py.allow_threads(|| prep_rust(text, &self.config)).map_err(to_py_err)
+    }
+
+    /// Preprocess a text and return its `ParsedText`, with its tokens.
+    #[pyo3(text_signature = "(self, text)")]
+    pub fn parse(&self, py: Python<'_>, text: String) -> PyResult<PyParsedText> {
+        py.allow_threads(|| parse_text_rust(text, &self.config)).map(PyParsedText::from).map_err(to_py_err)
+    }
+
+    /// Preprocess a list of texts on `n_jobs` threads and return the cleaned texts in order.
+    ///
+    /// * `n_jobs`: the number of threads, default to the available parallelism.
+    #[pyo3(signature = (texts, *, n_jobs=None), text_signature = "(self, texts, *, n_jobs=None)")]
+    pub fn process_batch(&self, py: Python<'_>, texts: Vec<String>, n_jobs: Option<usize>) -> PyResult<Vec<String>> {
+        let n_jobs = n_jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from));
+        py.allow_threads(|| prep_batch_rust(texts, &self.config, n_jobs)).map_err(to_py_err)
+    }
+
+    /// Preprocess every line, or record, of `filename` and write the results to `outfile`.
+    ///
+    /// * `threads`: the number of threads, default to the available parallelism.
+    /// * `chunk_size`: the number of lines given to a thread at once, default to 1000.
+    /// * `progress`: called as `progress(lines, lines_per_second)` after each chunk written, an
+    ///   exception stopping the processing, default to None.
+    /// * `file_format`: "text", "jsonl", "csv" or "tsv", default to "text".
+    /// * `text_field`: the field of the JSONL records, e.g. "retweeted_status.full_text", default
+    ///   to "text", or the name or index of the CSV column, default to "text".
+    /// * `output_field`: the field of the JSONL records receiving the cleaned text, `text_field`
+    ///   replacing the text, default to "clean_text".
+    /// * `extra_fields`: the fields added to the JSONL records, among "hashtags", "mentions",
+    ///   "urls" and "emojis", default to None.
+    /// * `delimiter`: the CSV delimiter, default to "," or a tab for "tsv".
+    /// * `header`: whether the CSV file starts with a header, default to True.
+    #[pyo3(
+        signature = (filename, outfile, *, threads=None, chunk_size=None, progress=None, file_format=None, text_field=None, output_field=None, extra_fields=None, delimiter=None, header=None),
+        text_signature = "(self, filename, outfile, *, threads=None, chunk_size=None, progress=None, file_format=None, text_field=None, output_field=None, extra_fields=None, delimiter=None, header=None)",
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_file(
+        &self,
+        py: Python<'_>,
+        filename: &str,
+        outfile: &str,
+        threads: Option<usize>,
+        chunk_size: Option<usize>,
+        progress: Option<PyObject>,
+        file_format: Option<&str>,
+        text_field: Option<&PyAny>,
+        output_field: Option<&str>,
+        extra_fields: Option<Vec<&str>>,
+        delimiter: Option<char>,
+        header: Option<bool>,
+    ) -> PyResult<()> {
+        let default = FileOptions::default();
+        let options = FileOptions {
+            format: build_format(file_format, text_field, output_field, extra_fields, delimiter, header)?,
+            threads: threads.unwrap_or(default.threads),
+            chunk_size: chunk_size.unwrap_or(default.chunk_size),
+        };
+        // the GIL is only held while the progress callback runs
+        let mut callback_err = None;
+        let result = py.allow_threads(|| {
+            prep_file_rust(filename, outfile, &self.config, &options, |p| match &progress {
+                Some(progress) => match Python::with_gil(|py| progress.call1(py, (p.lines, p.lines_per_second()))) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        callback_err = Some(err);
+                        false
+                    }
+                },
+                None => true,
+            })
+        });
+        match (result, callback_err) {
+            (Err(PrepError::Cancelled), Some(err)) => Err(err),
+            (result, _) => result.map_err(to_py_err),
+        }
+    }
+}
+
+impl PyPreprocessor {
+    /// A `Preprocessor` built from the keyword arguments of a module function.
+    pub(crate) fn from_kwargs<'py>(py: Python<'py>, options: Option<&'py PyDict>) -> PyResult<PyRef<'py, PyPreprocessor>> {
+        py.get_type::<PyPreprocessor>().call((), options)?.extract()
+    }
+}
+
+/// The strings of a Python iterable, e.g. a list or a set.
+fn extract_strings(strings: &PyAny) -> PyResult<Vec<String>> {
+    strings.iter()?.map(|string| string?.extract()).collect()
+}
+
+/// The `typography` option: True or False, a string like "quotes,apostrophes", or an iterable of categories.
+pub(crate) fn extract_typography(typography: &PyAny) -> PyResult<Typography> {
+    if let Ok(all) = typography.extract::<bool>() {
+        return Ok(if all { Typography::all() } else { Typography::default() });
+    }
+    let categories = match typography.extract::<String>() {
+        Ok(categories) => categories,
+        Err(_) => extract_strings(typography)?.join(","),
+    };
+    categories.parse().map_err(PyValueError::new_err)
+}
+
+/// The `segmenter` option: the path of a unigram table, or a dict of word counts.
+pub(crate) fn extract_segmenter(segmenter: &PyAny) -> PyResult<Segmenter> {
+    match segmenter.extract::<&str>() {
+        Ok(path) => Segmenter::from_file(path).map_err(to_py_err),
+        Err(_) => Ok(Segmenter::new(segmenter.extract::<HashMap<String, u64>>()?)),
+    }
+}
+
+/// A table option: a built-in language or a path, a table given by `table`, or an iterable of them,
+/// all merged in order.
+fn extract_lexicon<T: Lexicon>(
+    value: &PyAny,
+    table: fn(&PyAny) -> PyResult<Option<T>>,
+    expected: &str,
+) -> PyResult<Arc<T>> {
+    if let Ok(name) = value.extract::<&str>() {
+        return T::from_language_or_file(name).map_err(to_py_err);
+    }
+    if let Some(table) = table(value)? {
+        return Ok(Arc::new(table));
+    }
+    let mut merged = T::default();
+    for item in value.iter()? {
+        let item = item?;
+        if let Ok(name) = item.extract::<&str>() {
+            merged.merge(&*T::from_language_or_file(name).map_err(to_py_err)?);
+        } else if let Some(table) = table(item)? {
+            merged.merge(&table);
+        } else {
+            return Err(PyValueError::new_err(format!("expected a language, a path or {expected}")));
+        }
+    }
+    Ok(Arc::new(merged))
+}
+
+/// The `contractions` option: a built-in language or a path, a dict of expansions, or an iterable of them.
+pub(crate) fn extract_contractions(contractions: &PyAny) -> PyResult<Arc<Contractions>> {
+    let table = |value: &PyAny| match value.downcast::<PyDict>() {
+        Ok(expansions) => Ok(Some(Contractions::new(expansions.extract::<HashMap<String, String>>()?))),
+        Err(_) => Ok(None),
+    };
+    extract_lexicon(contractions, table, "a dict of contractions")
+}
+
+/// The `slang` option: a built-in language or a path, a dict of expansions, or an iterable of them.
+fn extract_slang(slang: &PyAny) -> PyResult<Arc<Slang>> {
+    let table = |value: &PyAny| match value.downcast::<PyDict>() {
+        Ok(expansions) => Ok(Some(Slang::new(expansions.extract::<HashMap<String, String>>()?))),
+        Err(_) => Ok(None),
+    };
+    extract_lexicon(slang, table, "a dict of slang")
+}
+
+/// The `stopwords` option: a built-in language or a path, a set of words, or an iterable of them.
+fn extract_stopwords(stopwords: &PyAny) -> PyResult<Arc<StopWords>> {
+    let table = |value: &PyAny| {
+        if value.is_instance_of::<PySet>()? || value.is_instance_of::<PyFrozenSet>()? {
+            Ok(Some(StopWords::new(extract_strings(value)?)))
+        } else {
+            Ok(None)
+        }
+    };
+    extract_lexicon(stopwords, table, "a set of stop words")
+}
+
+/// The format of `process_file`, "text", "jsonl", "csv" or "tsv".
+fn build_format(
+    file_format: Option<&str>,
+    text_field: Option<&PyAny>,
+    output_field: Option<&str>,
+    extra_fields: Option<Vec<&str>>,
+    delimiter: Option<char>,
+    header: Option<bool>,
+) -> PyResult<FileFormat> {
+    match file_format.unwrap_or("text") {
+        "text" => Ok(FileFormat::Text),
+        "jsonl" => {
+            let default = JsonlOptions::default();
+            let extra_fields = extra_fields.unwrap_or_default().into_iter()
+                .map(|field| field.parse::<ExtraField>())
+                .collect::<Result<_, _>>()
+                .map_err(PyValueError::new_err)?;
+            Ok(FileFormat::Jsonl(JsonlOptions {
+                field: text_field.map(|field| field.extract()).transpose()?.unwrap_or(default.field),
+                output_field: output_field.map(String::from).or(default.output_field),
+                extra_fields,
+            }))
+        }
+        format @ ("csv" | "tsv") => {
+            let default = if format == "tsv" { CsvOptions::tsv() } else { CsvOptions::default() };
+            let column = match text_field {
+                None => default.column,
+                Some(field) => match field.extract::<usize>() {
+                    Ok(index) => CsvColumn::Index(index),
+                    Err(_) => CsvColumn::Name(field.extract()?),
+                },
+            };
+            Ok(FileFormat::Csv(CsvOptions {
+                delimiter: delimiter.unwrap_or(default.delimiter),
+                header: header.unwrap_or(default.header),
+                column,
+            }))
+        }
+        other => Err(PyValueError::new_err(format!(r#"Unknown file format "{}", expected one of text, jsonl, csv, tsv"#, other))),
+    }
+}
@@ -1,19 +1,24 @@
 #![allow(dead_code, unused)]
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::os::raw::c_long;
 
-use ftnt::{text_parser::ParsedText, token::{Action, Token}, tokenizer::tweet_tokenize};
-use ftnt::text_parser::parse_text as parse_text_rust;
-use ftnt::text_parser::preprocess_text as preprocess_text_rust;
+use ftnt::alignment::Alignment;
+use ftnt::{config::PrepConfig, options::PrepOptions, text_parser::ParsedText, token::{ActionError, ActionKind, Token}, tokenizer::tweet_tokenize};
+use ftnt::text_parser::preprocess_text_with_config as preprocess_text_rust;
+use pyo3::exceptions::{PyIndexError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyList;
+use pyo3::basic::CompareOp;
+use pyo3::types::{PyBytes, PyDict, PyList, PySlice, PySliceIndices, PyTuple};
 use pcre2::bytes::Regex;
 
-use crate::token::PyToken;
+use crate::error::to_py_err;
+use crate::preprocessor::{extract_contractions, extract_typography, PyPreprocessor};
+use crate::token::{deepcopy_with_memo, extract_token, from_state, to_state, PyToken};
 
-#[pyclass(module = "faster_tweet_nlp_toolkit", name = "ParsedText")]
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[pyclass(module = "faster_tweet_nlp_toolkit", name = "ParsedText", text_signature = "(tokens=None, split=\" \")")]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct PyParsedText {
-    parsed_text: ParsedText,
+    pub(crate) parsed_text: ParsedText,
 }
 impl From<ParsedText> for PyParsedText {
     fn from(parsed_text: ParsedText) -> Self {
@@ -23,24 +28,143 @@ impl From<ParsedText> for PyParsedText {
 
 #[pymethods]
 impl PyParsedText {
+    /// A parsed text made of `tokens`, Tokens or strings, joined by `split`.
+    #[new]
+    #[pyo3(signature = (tokens=None, split=" "))]
+    pub fn new(tokens: Option<&PyAny>, split: &str) -> PyResult<PyParsedText> {
+        let tokens = match tokens {
+            Some(tokens) => tokens.iter()?.map(|token| extract_token(token?)).collect::<PyResult<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(ParsedText { tokens, split: split.to_string(), value: None, alignment: None }.into())
+    }
+
     fn __str__(&mut self) -> PyResult<String>   {
-        Ok(format!("{}", self.parsed_text.value()))
+        Ok(self.parsed_text.value().to_string())
     }
 
     fn __repr__(&mut self) -> PyResult<String>   {
         self.__str__()
     }
 
+    /// The tokens, as views modifying the parsed text in place.
     #[getter]
-    fn tokens(&self) -> Vec<PyToken> {
-        self.parsed_text.tokens.iter().map(|x| PyToken::from(x.clone())).collect()
+    fn tokens(slf: &PyCell<Self>) -> Vec<PyToken> {
+        let len = slf.borrow().parsed_text.len();
+        (0..len).map(|i| PyToken::view(slf.into(), i)).collect()
+    }
+
+    /// The number of tokens.
+    pub fn __len__(&self) -> usize {
+        self.parsed_text.len()
+    }
+
+    /// The view on a token, or a new parsed text with a copy of a slice of the tokens.
+    fn __getitem__(slf: &PyCell<Self>, index: &PyAny) -> PyResult<PyObject> {
+        let py = slf.py();
+        let this = slf.borrow();
+        let parsed_text = &this.parsed_text;
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let tokens = slice_indices(slice, parsed_text.len())?.into_iter().map(|i| parsed_text[i].clone()).collect();
+            let sliced = ParsedText { tokens, split: parsed_text.split.clone(), value: None, alignment: None };
+            return Ok(PyParsedText::from(sliced).into_py(py));
+        }
+        let i = token_index(index.extract()?, parsed_text.len())?;
+        Ok(PyToken::view(slf.into(), i).into_py(py))
+    }
+
+    /// Replace a token, or a slice of the tokens, by Tokens or strings.
+    fn __setitem__(slf: &PyCell<Self>, index: &PyAny, value: &PyAny) -> PyResult<()> {
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let tokens = value.iter()?.map(|token| extract_token(token?)).collect::<PyResult<Vec<_>>>()?;
+            let mut this = slf.try_borrow_mut()?;
+            let parsed_text = &mut this.parsed_text;
+            let PySliceIndices { start, step, slicelength, .. } = slice.indices(parsed_text.len() as c_long)?;
+            let indices = slice_indices(slice, parsed_text.len())?;
+            if step == 1 {
+                // like a list, the tokens can be more or fewer than the replaced ones
+                parsed_text.tokens.splice(start as usize..(start + slicelength) as usize, tokens);
+            } else if indices.len() == tokens.len() {
+                for (i, token) in indices.into_iter().zip(tokens) {
+                    parsed_text.tokens[i] = token;
+                }
+            } else {
+                return Err(PyValueError::new_err(format!(
+                    "attempt to assign sequence of size {} to extended slice of size {}", tokens.len(), indices.len(),
+                )));
+            }
+            parsed_text.value = None;
+            return Ok(());
+        }
+        let token = extract_token(value)?;
+        let mut this = slf.try_borrow_mut()?;
+        let i = token_index(index.extract()?, this.parsed_text.len())?;
+        this.parsed_text.tokens[i] = token;
+        this.parsed_text.value = None;
+        Ok(())
+    }
+
+    /// Remove a token, or a slice of the tokens.
+    fn __delitem__(&mut self, index: &PyAny) -> PyResult<()> {
+        let parsed_text = &mut self.parsed_text;
+        let mut indices = match index.downcast::<PySlice>() {
+            Ok(slice) => slice_indices(slice, parsed_text.len())?,
+            Err(_) => vec![token_index(index.extract()?, parsed_text.len())?],
+        };
+        indices.sort_unstable();
+        for i in indices.into_iter().rev() {
+            parsed_text.tokens.remove(i);
+        }
+        parsed_text.value = None;
+        Ok(())
+    }
+
+    /// Insert a Token or a string before `index`.
+    #[pyo3(text_signature = "(self, index, token)")]
+    fn insert(slf: &PyCell<Self>, index: isize, token: &PyAny) -> PyResult<()> {
+        let token = extract_token(token)?;
+        let mut this = slf.try_borrow_mut()?;
+        let len = this.parsed_text.len() as isize;
+        let i = if index < 0 { (index + len).max(0) } else { index.min(len) };
+        this.parsed_text.tokens.insert(i as usize, token);
+        this.parsed_text.value = None;
+        Ok(())
+    }
+
+    /// Append a Token or a string.
+    #[pyo3(text_signature = "(self, token)")]
+    fn append(slf: &PyCell<Self>, token: &PyAny) -> PyResult<()> {
+        let len = slf.borrow().parsed_text.len() as isize;
+        PyParsedText::insert(slf, len, token)
+    }
+
+    /// Iterate over the views on the tokens.
+    fn __iter__(slf: &PyCell<Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+        let tokens = PyList::new(py, PyParsedText::tokens(slf).into_iter().map(|token| token.into_py(py)));
+        Ok(tokens.as_ref().iter()?.into_py(py))
     }
 
-    pub fn __len__(&mut self) -> PyResult<usize> {
-        Ok(self.parsed_text.value().len())
+    /// Whether a token is equal to a Token, or has the value of a string.
+    fn __contains__(&self, item: &PyAny) -> PyResult<bool> {
+        let tokens = &self.parsed_text.tokens;
+        match item.extract::<PyRef<PyToken>>() {
+            Ok(token) => {
+                let token = token.to_token()?;
+                Ok(tokens.contains(&token))
+            }
+            Err(_) => match item.extract::<&str>() {
+                Ok(value) => Ok(tokens.iter().any(|token| token.value == value)),
+                Err(_) => Ok(false),
+            },
+        }
     }
 
-    #[pyo3(text_signature = "(self, mentions_action, hashtags_action, urls_action, digits_action, emojis_action, emoticons_action, puncts_action, emails_action, html_tags_action)")]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(
+        signature = (mentions_action=None, hashtags_action=None, urls_action=None, digits_action=None, emojis_action=None, emoticons_action=None, puncts_action=None, emails_action=None, html_tags_action=None),
+        text_signature = "(self, mentions_action=None, hashtags_action=None, urls_action=None, digits_action=None, emojis_action=None, emoticons_action=None, puncts_action=None, emails_action=None, html_tags_action=None)",
+    )]
     pub fn process(
         &mut self,
         mentions_action: Option<&str>,
@@ -52,107 +176,228 @@ impl PyParsedText {
         puncts_action: Option<&str>,
         emails_action: Option<&str>,
         html_tags_action: Option<&str>,
-    ) -> () {
-        return self.parsed_text.process(mentions_action, hashtags_action, urls_action, digits_action, emojis_action, emoticons_action, puncts_action, emails_action, html_tags_action)
+    ) -> PyResult<()> {
+        let mut options = PrepOptions::default();
+        for (action, option) in [
+            (mentions_action, &mut options.mentions),
+            (hashtags_action, &mut options.hashtags),
+            (urls_action, &mut options.urls),
+            (digits_action, &mut options.digits),
+            (emojis_action, &mut options.emojis),
+            (emoticons_action, &mut options.emoticons),
+            (puncts_action, &mut options.puncts),
+            (emails_action, &mut options.emails),
+            (html_tags_action, &mut options.html_tags),
+        ] {
+            if let Some(action) = action {
+                *option = Some(parse_action(action)?);
+            }
+        }
+        let config = options.build().map_err(to_py_err)?;
+        self.parsed_text.process_with_config(&config).map_err(to_py_err)
     }
 
-    pub fn post_process(&mut self) -> () {
-        return self.parsed_text.post_process()
+    pub fn post_process(&mut self) -> PyResult<()> {
+        self.parsed_text.post_process().map_err(to_py_err)
+    }
+
+    /// The text after the preprocessing, before the tokenization.
+    #[getter]
+    pub fn clean_text(&self) -> Option<&str> {
+        self.parsed_text.alignment.as_ref().map(|alignment| alignment.text())
+    }
+
+    /// Project the chars `char_start..char_end` of `clean_text` onto the raw text, as a pair of char offsets.
+    #[pyo3(text_signature = "(self, char_start, char_end)")]
+    pub fn project(&self, char_start: usize, char_end: usize) -> Option<(usize, usize)> {
+        self.parsed_text.alignment.as_ref()
+            .and_then(|alignment| alignment.project_chars(char_start, char_end))
+            .map(|span| (span.char_start, span.char_end))
     }
 
     #[getter]
     pub fn value(&mut self) -> &str {
-        return self.parsed_text.value()
+        self.parsed_text.value()
     }
 
     #[getter]
     pub fn hashtags(&self) -> Vec<String> {
-        return self.parsed_text.hashtags()
+        self.parsed_text.hashtags()
+    }
+
+    #[getter]
+    pub fn cashtags(&self) -> Vec<String> {
+        self.parsed_text.cashtags()
     }
 
     #[getter]
     pub fn mentions(&self) -> Vec<String> {
-        return self.parsed_text.mentions()
+        self.parsed_text.mentions()
     }
 
     #[getter]
     pub fn emojis(&self) -> Vec<String> {
-        return self.parsed_text.emojis()
+        self.parsed_text.emojis()
     }
 
     #[getter]
     pub fn digits(&self) -> Vec<String> {
-        return self.parsed_text.digits()
+        self.parsed_text.digits()
     }
 
     #[getter]
     pub fn emails(&self) -> Vec<String> {
-        return self.parsed_text.emails()
+        self.parsed_text.emails()
     }
 
     #[getter]
     pub fn urls(&self) -> Vec<String> {
-        return self.parsed_text.urls()
+        self.parsed_text.urls()
+    }
+
+    /// Whether the raw text starts with the `RT @user: ` prefix of a retweet, whatever the
+    /// `retweet` option did with it.
+    pub fn is_retweet(&self) -> bool {
+        self.parsed_text.is_retweet()
+    }
+
+    /// The user of the `RT @user: ` prefix, without the '@', None if the text isn't a retweet.
+    pub fn retweeted_user(&self) -> Option<&str> {
+        self.parsed_text.retweeted_user()
+    }
+
+    /// Parsed texts are equal if their tokens, values and alignments are.
+    /// Without a `__hash__`, the parsed text is unhashable like a list, as it is mutable.
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+        match (other.extract::<PyRef<PyParsedText>>(), op) {
+            (Ok(other), CompareOp::Eq) => (self.key() == other.key()).into_py(py),
+            (Ok(other), CompareOp::Ne) => (self.key() != other.key()).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __copy__(&self) -> PyParsedText {
+        self.clone()
+    }
+
+    /// A copy of the parsed text, the same one for every reference to it within the copied object.
+    fn __deepcopy__(slf: &PyCell<Self>, memo: &PyDict) -> PyResult<PyObject> {
+        deepcopy_with_memo(slf, memo, |parsed_text| Ok(parsed_text.clone()))
+    }
+
+    /// The tokens, the value and the alignment as JSON bytes.
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        to_state(py, &self.parsed_text)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        self.parsed_text = from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__(slf: &PyCell<Self>) -> PyResult<&PyTuple> {
+        let py = slf.py();
+        let state = slf.borrow().__getstate__(py)?;
+        Ok(PyTuple::new(py, [slf.get_type().to_object(py), PyTuple::empty(py).to_object(py), state.to_object(py)]))
     }
 }
 
+/// The index of a token, negative indices counting from the end.
+fn token_index(index: isize, len: usize) -> PyResult<usize> {
+    let i = if index < 0 { index + len as isize } else { index };
+    if 0 <= i && (i as usize) < len {
+        Ok(i as usize)
+    } else {
+        Err(PyIndexError::new_err("ParsedText index out of range"))
+    }
+}
+
+/// The indices of the tokens in a slice, in the order of the slice.
+fn slice_indices(slice: &PySlice, len: usize) -> PyResult<Vec<usize>> {
+    let indices = slice.indices(len as c_long)?;
+    Ok((0..indices.slicelength).map(|i| (indices.start + i * indices.step) as usize).collect())
+}
+
+impl PyParsedText {
+    /// What the comparisons look at, the value being computed if it isn't cached yet.
+    fn key(&self) -> (&[Token], &str, Cow<'_, str>, &Option<Alignment>) {
+        let parsed_text = &self.parsed_text;
+        let value = match &parsed_text.value {
+            Some(value) => Cow::Borrowed(value.as_str()),
+            None => Cow::Owned(parsed_text.tokens.iter().map(|token| token.value.as_str()).collect::<Vec<_>>().join(&parsed_text.split)),
+        };
+        (&parsed_text.tokens, &parsed_text.split, value, &parsed_text.alignment)
+    }
+}
+
+/// Parse the name of an action, an unknown name raising a `ValueError`.
+pub(crate) fn parse_action(action: &str) -> PyResult<ActionKind> {
+    action.parse().map_err(|err: ActionError| PyValueError::new_err(err.to_string()))
+}
+
+/// The options of a preset or of a config file as a dict, e.g. to record them in a dataset card.
 #[pyfunction]
-#[pyo3(text_signature="(text, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len, filters, emojis, emoticons, mentions, hashtags, urls, digits, puncts, emails, html_tags)")]
-pub fn parse_text(
-    text: String,
-    encoding: Option<&str>,
-    remove_unencodable_char: Option<bool>,
-    to_lower: Option<bool>,
-    strip_accents: Option<bool>,
-    reduce_len: Option<bool>,
-    filters: Option<HashSet<&str>>,
-    emojis: Option<&str>,
-    emoticons: Option<&str>,
-    mentions: Option<&str>,
-    hashtags: Option<&str>,
-    urls: Option<&str>,
-    digits: Option<&str>,
-    puncts: Option<&str>,
-    emails: Option<&str>,
-    html_tags: Option<&str>,
-) -> PyParsedText{
-    PyParsedText::from(parse_text_rust(
-        text,
-        encoding,
-        remove_unencodable_char,
-        to_lower,
-        strip_accents,
-        reduce_len,
-        Some(tweet_tokenize),
-        filters,
-        emojis,
-        emoticons,
-        mentions,
-        hashtags,
-        urls,
-        digits,
-        puncts,
-        emails,
-        html_tags
-    )).into()
+#[pyo3(text_signature="(config)")]
+pub fn load_config(py: Python<'_>, config: &str) -> PyResult<PyObject> {
+    let options = PrepOptions::from_preset_or_file(config).map_err(to_py_err)?;
+    let json = serde_json::to_string(&options).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/// Save a dict of options, e.g. from `load_config`, to a TOML, JSON or YAML file.
+#[pyfunction]
+#[pyo3(text_signature="(options, path)")]
+pub fn save_config(py: Python<'_>, options: &PyAny, path: &str) -> PyResult<()> {
+    let json: String = py.import("json")?.call_method1("dumps", (options,))?.extract()?;
+    let options: PrepOptions = serde_json::from_str(&json).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    options.save(path).map_err(to_py_err)
 }
 
+/// Preprocess a text and return its `ParsedText`.
+///
+/// The keyword-only options and their defaults are those of `Preprocessor`, an unknown option
+/// raising a `TypeError`.
 #[pyfunction]
-#[pyo3(text_signature="(text, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len)")]
+#[pyo3(signature = (text, **options), text_signature = "(text, *, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None)")]
+pub fn parse_text(py: Python<'_>, text: String, options: Option<&PyDict>) -> PyResult<PyParsedText> {
+    PyPreprocessor::from_kwargs(py, options)?.parse(py, text)
+}
+
+/// Preprocess a text without tokenizing it, `typography` and `contractions` being those of `Preprocessor`.
+#[pyfunction]
+#[pyo3(
+    signature = (text, *, encoding=None, remove_unencodable_char=false, to_lower=true, strip_accents=false, reduce_len=false, typography=None, contractions=None),
+    text_signature = "(text, *, encoding=None, remove_unencodable_char=False, to_lower=True, strip_accents=False, reduce_len=False, typography=None, contractions=None)",
+)]
+#[allow(clippy::too_many_arguments)]
 pub fn preprocess_text(
-    mut text: String,
+    py: Python<'_>,
+    text: String,
     encoding: Option<&str>,
-    remove_unencodable_char: Option<bool>,
-    to_lower: Option<bool>,
-    strip_accents: Option<bool>,
-    reduce_len: Option<bool>,
-) -> String {
-    preprocess_text_rust(
-        text,
-        encoding,
-        remove_unencodable_char,
-        to_lower,
-        strip_accents,
-        reduce_len,
-    )
+    remove_unencodable_char: bool,
+    to_lower: bool,
+    strip_accents: bool,
+    reduce_len: bool,
+    typography: Option<&PyAny>,
+    contractions: Option<&PyAny>,
+) -> PyResult<String> {
+    let mut builder = PrepConfig::builder();
+    if let Some(encoding) = encoding {
+        builder.encoding(encoding);
+    }
+    if let Some(typography) = typography {
+        builder.typography(extract_typography(typography)?);
+    }
+    if let Some(contractions) = contractions {
+        builder.contractions(extract_contractions(contractions)?);
+    }
+    let config = builder
+        .remove_unencodable_char(remove_unencodable_char)
+        .to_lower(to_lower)
+        .strip_accents(strip_accents)
+        .reduce_len(reduce_len)
+        .build()
+        .map_err(|err| to_py_err(err.into()))?;
+    py.allow_threads(|| preprocess_text_rust(text, &config)).map_err(to_py_err)
 }
@@ -1,53 +1,54 @@
-use std::collections::HashSet;
-
 use pyo3::prelude::*;
-use ftnt::text_prep::prep as prep_rust;
-use ftnt::text_prep::prep_file as prep_file_rust;
-use ftnt::tokenizer::tweet_tokenize;
+use pyo3::types::PyDict;
+
+use crate::preprocessor::PyPreprocessor;
+
+/// Preprocess a text and return the cleaned text.
+///
+/// The keyword-only options and their defaults are those of `Preprocessor`, an unknown option
+/// raising a `TypeError`.
+#[pyfunction]
+#[pyo3(signature = (text, **options), text_signature = "(text, *, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None)")]
+pub fn prep(py: Python<'_>, text: String, options: Option<&PyDict>) -> PyResult<String> {
+    PyPreprocessor::from_kwargs(py, options)?.__call__(py, text)
+}
 
+/// Preprocess a list of texts on `n_jobs` threads and return the cleaned texts in order.
+///
+/// `n_jobs` defaults to the available parallelism. The other keyword-only options and their
+/// defaults are those of `Preprocessor`, an unknown option raising a `TypeError`.
 #[pyfunction]
-#[pyo3(text_signature="(text, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len, filters, emojismentions, hashtags, urls, digits, puncts, emails, html_tags)")]
-pub fn prep(
-    text: String,
-    encoding: Option<&str>,
-    remove_unencodable_char: Option<bool>,
-    to_lower: Option<bool>,
-    strip_accents: Option<bool>,
-    reduce_len: Option<bool>,
-    filters: Option<HashSet<&str>>,
-    emojis: Option<&str>,
-    emoticons: Option<&str>,
-    mentions: Option<&str>,
-    hashtags: Option<&str>,
-    urls: Option<&str>,
-    digits: Option<&str>,
-    puncts: Option<&str>,
-    emails: Option<&str>,
-    html_tags: Option<&str>,
-)-> String {
-    prep_rust(text, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len, Some(tweet_tokenize), filters, emojis, emoticons, mentions, hashtags, urls, digits, puncts, emails, html_tags)
+#[pyo3(signature = (texts, *, n_jobs=None, **options), text_signature = "(texts, *, n_jobs=None, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None)")]
+pub fn prep_batch(py: Python<'_>, texts: Vec<String>, n_jobs: Option<usize>, options: Option<&PyDict>) -> PyResult<Vec<String>> {
+    PyPreprocessor::from_kwargs(py, options)?.process_batch(py, texts, n_jobs)
 }
 
+/// Preprocess every line, or record, of `filename` and write the results to `outfile`.
+///
+/// The file options are those of `Preprocessor.process_file`. The other keyword-only options and
+/// their defaults are those of `Preprocessor`, an unknown option raising a `TypeError`.
 #[pyfunction]
-#[pyo3(text_signature="(filename, outfile, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len, filters, emojismentions, hashtags, urls, digits, puncts, emails, html_tags)")]
+#[pyo3(
+    signature = (filename, outfile, *, threads=None, chunk_size=None, progress=None, file_format=None, text_field=None, output_field=None, extra_fields=None, delimiter=None, header=None, **options),
+    text_signature = "(filename, outfile, *, threads=None, chunk_size=None, progress=None, file_format=None, text_field=None, output_field=None, extra_fields=None, delimiter=None, header=None, config=None, encoding=None, remove_unencodable_char=None, typography=None, to_lower=None, strip_accents=None, reduce_len=None, max_repeat=None, protect_tokens=None, vocabulary=None, retweet=None, contractions=None, tokenizer=None, token_pattern=None, filters=None, slang=None, slang_mode=None, stopwords=None, keep_negations=None, segmenter=None, emojis=None, emoticons=None, mentions=None, hashtags=None, cashtags=None, urls=None, digits=None, puncts=None, emails=None, html_tags=None)",
+)]
+#[allow(clippy::too_many_arguments)]
 pub fn prep_file(
+    py: Python<'_>,
     filename: &str,
     outfile: &str,
-    encoding: Option<&str>,
-    remove_unencodable_char: Option<bool>,
-    to_lower: Option<bool>,
-    strip_accents: Option<bool>,
-    reduce_len: Option<bool>,
-    filters: Option<HashSet<&str>>,
-    emojis: Option<&str>,
-    emoticons: Option<&str>,
-    mentions: Option<&str>,
-    hashtags: Option<&str>,
-    urls: Option<&str>,
-    digits: Option<&str>,
-    puncts: Option<&str>,
-    emails: Option<&str>,
-    html_tags: Option<&str>,
-) {
-    prep_file_rust(filename, outfile, encoding, remove_unencodable_char, to_lower, strip_accents, reduce_len, Some(tweet_tokenize), filters, emojis, emoticons, mentions, hashtags, urls, digits, puncts, emails, html_tags)
-}
\ No newline at end of file
+    threads: Option<usize>,
+    chunk_size: Option<usize>,
+    progress: Option<PyObject>,
+    file_format: Option<&str>,
+    text_field: Option<&PyAny>,
+    output_field: Option<&str>,
+    extra_fields: Option<Vec<&str>>,
+    delimiter: Option<char>,
+    header: Option<bool>,
+    options: Option<&PyDict>,
+) -> PyResult<()> {
+    PyPreprocessor::from_kwargs(py, options)?.process_file(
+        py, filename, outfile, threads, chunk_size, progress, file_format, text_field, output_field, extra_fields, delimiter, header,
+    )
+}
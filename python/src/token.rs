@@ -1,24 +1,88 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use std::sync::Arc;
+
 use ftnt::prep::token::*;
+use pyo3::basic::CompareOp;
+use pyo3::exceptions::{PyIndexError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyTuple};
+use pyo3::{AsPyPointer, PyClass};
 use core::iter::Iterator;
 
+use crate::error::to_py_err;
+use crate::preprocessor::extract_segmenter;
+use crate::text_parser::PyParsedText;
+
 #[pyclass(module = "faster_tweet_nlp_toolkit", name = "Token")]
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct PyToken {
-    token: Token,
+    token: TokenRef,
 }
+
+/// A token of its own, or a view on a token of a `ParsedText`.
+#[derive(Debug, Clone)]
+enum TokenRef {
+    Owned(Token),
+    /// The token at this index of the parsed text, read and modified in place.
+    View(Py<PyParsedText>, usize),
+}
+
 impl From<Token> for PyToken {
     fn from(token: Token) -> Self {
-        Self { token }
+        Self { token: TokenRef::Owned(token) }
+    }
+}
+
+impl PyToken {
+    /// A view on the token at `index` of `parsed_text`.
+    pub(crate) fn view(parsed_text: Py<PyParsedText>, index: usize) -> PyToken {
+        Self { token: TokenRef::View(parsed_text, index) }
+    }
+
+    /// Call `f` with the token, or with the token of the parsed text a view is on.
+    fn with_token<R>(&self, f: impl FnOnce(&Token) -> R) -> PyResult<R> {
+        match &self.token {
+            TokenRef::Owned(token) => Ok(f(token)),
+            TokenRef::View(parsed_text, index) => Python::with_gil(|py| {
+                let parsed_text = parsed_text.try_borrow(py)?;
+                parsed_text.parsed_text.tokens.get(*index).map(f).ok_or_else(|| removed(*index))
+            }),
+        }
     }
+
+    /// Call `f` with the token to modify, a view invalidating the value of its parsed text.
+    fn with_token_mut<R>(&mut self, f: impl FnOnce(&mut Token) -> R) -> PyResult<R> {
+        match &mut self.token {
+            TokenRef::Owned(token) => Ok(f(token)),
+            TokenRef::View(parsed_text, index) => Python::with_gil(|py| {
+                let mut parsed_text = parsed_text.try_borrow_mut(py)?;
+                let parsed_text = &mut parsed_text.parsed_text;
+                let token = parsed_text.tokens.get_mut(*index).ok_or_else(|| removed(*index))?;
+                let result = f(token);
+                parsed_text.value = None;
+                Ok(result)
+            }),
+        }
+    }
+
+    /// A copy of the token.
+    pub(crate) fn to_token(&self) -> PyResult<Token> {
+        self.with_token(Token::clone)
+    }
+}
+
+/// The error of a view on a token removed from its parsed text.
+fn removed(index: usize) -> PyErr {
+    PyIndexError::new_err(format!("the token {} was removed from its parsed text", index))
 }
 
-impl From<PyToken> for Token {
-    fn from(token: PyToken) -> Self {
-        token.token
+/// A token given to a `ParsedText`, either a `Token` or a string.
+pub(crate) fn extract_token(token: &PyAny) -> PyResult<Token> {
+    match token.extract::<PyRef<PyToken>>() {
+        Ok(token) => token.to_token(),
+        Err(_) => Token::try_new(token.extract()?).map_err(to_py_err),
     }
 }
 
@@ -31,7 +95,7 @@ struct MyIterator {
 impl MyIterator {
     #[allow(clippy::self_named_constructors)]
     fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
-        slf.into()
+        slf
     }
     fn __next__(&mut self) -> Option<char> {
         self.iter.next()
@@ -41,32 +105,34 @@ impl MyIterator {
 #[pymethods]
 impl PyToken {
     #[new]
-    pub fn new(value: String) -> PyToken {
-        Token::new(value).into()
+    pub fn new(value: String) -> PyResult<PyToken> {
+        Ok(Token::try_new(value).map_err(to_py_err)?.into())
     }
     // String like operations
     pub fn __add__(&self, other: String) -> PyResult<String> {
-        Ok(concat_string!(self.token.value, other))
+        self.with_token(|token| concat_string!(token.value, other))
     }
 
     pub fn __radd__(&self, other: String) -> PyResult<String> {
-        Ok(concat_string!(other, self.token.value))
+        self.with_token(|token| concat_string!(other, token.value))
     }
 
     pub fn __mul_func(&self, val: isize) -> PyResult<String> {
         if val <= 0 {
             Ok("".to_string())
         } else {
-            Ok(self.token.value.repeat(val.try_into().unwrap()))
+            self.with_token(|token| token.value.repeat(val.try_into().unwrap()))
         }
     }
 
-    pub fn __iadd__(&mut self, other: String) -> () {
-        self.set_value(self.__add__(other).unwrap())
+    pub fn __iadd__(&mut self, other: String) -> PyResult<()> {
+        let value = self.__add__(other)?;
+        self.set_value(value)
     }
 
-    pub fn __imul__(&mut self, val: isize) -> () {
-        self.set_value(self.__mul_func(val).unwrap())
+    pub fn __imul__(&mut self, val: isize) -> PyResult<()> {
+        let value = self.__mul_func(val)?;
+        self.set_value(value)
     }
 
     pub fn __mul__(&self, val: isize) -> PyResult<String> {
@@ -78,24 +144,22 @@ impl PyToken {
     }
 
     fn __iter__(&self) -> PyResult<MyIterator> {
-        let iter = MyIterator {
-            iter: Box::new(self.token.value.chars().collect::<Vec<_>>().into_iter()),
-        };
-        Ok(iter)
+        let chars = self.with_token(|token| token.value.chars().collect::<Vec<_>>())?;
+        Ok(MyIterator { iter: Box::new(chars.into_iter()) })
     }
 
     #[getter]
     fn get_value(&self) -> PyResult<String> {
-        Ok(self.token.value.clone())
+        self.with_token(|token| token.value.clone())
     }
 
     #[setter]
-    pub fn set_value(&mut self, new_value: String) -> () {
-        self.token.set_value(new_value);
+    pub fn set_value(&mut self, new_value: String) -> PyResult<()> {
+        self.with_token_mut(|token| token.try_set_value(new_value))?.map_err(to_py_err)
     }
 
     fn __str__(&self) -> PyResult<String>   {
-        Ok(format!("\"{}\"", self.token.value))
+        self.with_token(|token| format!("\"{}\"", token.value))
     }
 
     fn __repr__(&self) -> PyResult<String>   {
@@ -103,51 +167,176 @@ impl PyToken {
     }
 
     fn __len__(&self) -> PyResult<usize> {
-        Ok(self.token.value.len())
+        self.with_token(|token| token.value.len())
+    }
+
+    fn __getitem__(&self, idx: usize) -> PyResult<String> {
+        self.with_token(|token| token.value.chars().nth(idx))?
+            .map(String::from)
+            .ok_or_else(|| PyIndexError::new_err("string index out of range"))
+    }
+
+    pub fn is_punct(&self) -> PyResult<bool> {
+        self.with_token(Token::is_punct)
+    }
+
+    pub fn is_emoji(&self) -> PyResult<bool> {
+        self.with_token(Token::is_emoji)
+    }
+
+    pub fn is_hashtag(&self) -> PyResult<bool> {
+        self.with_token(Token::is_hashtag)
+    }
+    pub fn is_cashtag(&self) -> PyResult<bool> {
+        self.with_token(Token::is_cashtag)
+    }
+    pub fn is_url(&self) -> PyResult<bool> {
+        self.with_token(Token::is_url)
+    }
+    pub fn is_mention(&self) -> PyResult<bool> {
+        self.with_token(Token::is_mention)
     }
 
-    fn __getitem__(&mut self, idx: usize) -> PyResult<String> {
-        Ok(self.token.index_at(idx).to_string())
+    pub fn is_digit(&self) -> PyResult<bool> {
+        self.with_token(Token::is_digit)
     }
 
-    pub fn is_punct(&self) -> bool {
-        self.token.is_punct()
+    pub fn is_email(&self) -> PyResult<bool> {
+        self.with_token(Token::is_email)
     }
 
-    pub fn is_emoji(&self) -> bool {
-        self.token.is_emoji()
+    pub fn is_html_tag(&self) -> PyResult<bool> {
+        self.with_token(Token::is_html_tag)
     }
 
-    pub fn is_hashtag(&self) -> bool {
-        self.token.is_hashtag()
+    pub fn is_emoticon(&self) -> PyResult<bool> {
+        self.with_token(Token::is_emoticon)
     }
-    pub fn is_url(&self) -> bool {
-        self.token.is_url()
+
+    pub fn is_word(&self) -> PyResult<bool> {
+        self.with_token(Token::is_word)
     }
-    pub fn is_mention(&self) -> bool {
-        self.token.is_mention()
+
+    /// Whether the token is one of the `stopwords` of the `Preprocessor` which parsed it.
+    pub fn is_stop_word(&self) -> PyResult<bool> {
+        self.with_token(Token::is_stop_word)
     }
 
-    pub fn is_digit(&self) -> bool {
-        self.token.is_digit()
+    /// The kind of the token computed during the tokenization, e.g. "hashtag".
+    #[getter]
+    fn kind(&self) -> PyResult<&'static str> {
+        self.with_token(|token| token.kind.as_str())
     }
 
-    pub fn is_email(&self) -> bool {
-        self.token.is_email()
+    /// The expansion the slang normalization found for the token, e.g. "i do not know" for "idk", else None.
+    #[getter]
+    fn expansion(&self) -> PyResult<Option<String>> {
+        self.with_token(|token| token.expansion.clone())
     }
 
-    pub fn is_html_tag(&self) -> bool {
-        self.token.is_html_tag()
+    /// The byte offset where the token starts in the raw text, None for a token created by hand.
+    #[getter]
+    fn start(&self) -> PyResult<Option<usize>> {
+        self.with_token(|token| token.span.map(|span| span.start))
+    }
+
+    /// The byte offset where the token ends in the raw text.
+    #[getter]
+    fn end(&self) -> PyResult<Option<usize>> {
+        self.with_token(|token| token.span.map(|span| span.end))
+    }
+
+    /// The char offset where the token starts in the raw text, to slice the Python string.
+    #[getter]
+    fn char_start(&self) -> PyResult<Option<usize>> {
+        self.with_token(|token| token.span.map(|span| span.char_start))
+    }
+
+    /// The char offset where the token ends in the raw text.
+    #[getter]
+    fn char_end(&self) -> PyResult<Option<usize>> {
+        self.with_token(|token| token.span.map(|span| span.char_end))
     }
 
     #[pyo3(text_signature = "(self, action)")]
-    pub fn do_action(&mut self, action: &PyAction) -> bool {
-        self.token.do_action(&action.action)
+    pub fn do_action(&mut self, action: &PyAction) -> PyResult<bool> {
+        self.with_token_mut(|token| token.try_do_action(&action.action))?.map_err(to_py_err)
+    }
+
+    /// Tokens are equal if their values, kinds and spans are.
+    /// Without a `__hash__`, the token is unhashable like a list, as it is mutable.
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        match (other.extract::<PyRef<PyToken>>(), op) {
+            (Ok(other), CompareOp::Eq) => Ok((self.to_token()? == other.to_token()?).into_py(py)),
+            (Ok(other), CompareOp::Ne) => Ok((self.to_token()? != other.to_token()?).into_py(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    /// A copy of the token, a view giving a token of its own.
+    fn __copy__(&self) -> PyResult<PyToken> {
+        self.to_token().map(PyToken::from)
+    }
+
+    fn __deepcopy__(slf: &PyCell<Self>, memo: &PyDict) -> PyResult<PyObject> {
+        deepcopy_with_memo(slf, memo, PyToken::__copy__)
+    }
+
+    /// The token, with its kind and span, as JSON bytes.
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        to_state(py, &self.to_token()?)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        let state: Token = from_state(state)?;
+        self.with_token_mut(|token| *token = state)
+    }
+
+    /// A view is pickled as a token of its own.
+    fn __reduce__(slf: &PyCell<Self>) -> PyResult<&PyTuple> {
+        let py = slf.py();
+        let token = slf.borrow().to_token()?;
+        let state = to_state(py, &token)?;
+        let args = PyTuple::new(py, [token.value.as_str()]);
+        Ok(PyTuple::new(py, [slf.get_type().to_object(py), args.to_object(py), state.to_object(py)]))
     }
 }
 
+/// The `__deepcopy__` of a pyclass: the copy already in `memo` for this object, or else a new one
+/// recorded there, so that the copied object keeps its shared references.
+pub(crate) fn deepcopy_with_memo<T: PyClass + Into<PyClassInitializer<T>>>(
+    slf: &PyCell<T>,
+    memo: &PyDict,
+    copy: impl FnOnce(&T) -> PyResult<T>,
+) -> PyResult<PyObject> {
+    let py = slf.py();
+    let id = slf.as_ptr() as usize;
+    if let Some(copied) = memo.get_item(id) {
+        return Ok(copied.into_py(py));
+    }
+    let copied = Py::new(py, copy(&slf.borrow())?)?.into_py(py);
+    memo.set_item(id, &copied)?;
+    Ok(copied)
+}
 
-#[pyclass(module = "faster_tweet_nlp_toolkit", name = "Action")]
+/// A Rust value as the JSON bytes `__getstate__` returns.
+pub(crate) fn to_state<'py, T: serde::Serialize>(py: Python<'py>, value: &T) -> PyResult<&'py PyBytes> {
+    let state = serde_json::to_vec(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(PyBytes::new(py, &state))
+}
+
+/// A Rust value from the JSON bytes `__setstate__` receives.
+pub(crate) fn from_state<'de, T: serde::Deserialize<'de>>(state: &'de [u8]) -> PyResult<T> {
+    serde_json::from_slice(state).map_err(|err| PyValueError::new_err(format!("invalid state: {}", err)))
+}
+
+
+/// An action applied to the tokens matching a condition, e.g. `Action("tag", "is_hashtag")`.
+///
+/// `segmenter` is the word counts the "segment" action splits the hashtags with, or the path of a
+/// file of `word count` lines, default to the built-in English unigrams.
+#[pyclass(module = "faster_tweet_nlp_toolkit", name = "Action", text_signature = "(action_name, action_condition, *, segmenter=None)")]
 pub struct PyAction {
     action: Action,
 }
@@ -166,32 +355,49 @@ impl From<PyAction> for Action {
 
 #[pymethods]
 impl PyAction {
-    #[pyo3(text_signature = "(self, token)")]
-    fn remove(&self, token: &mut PyToken) -> () {
-        token.set_value("".to_string())
+    #[new]
+    #[pyo3(signature = (action_name, action_condition, *, segmenter=None))]
+    pub fn new(action_name: &str, action_condition: &str, segmenter: Option<&PyAny>) -> PyResult<PyAction> {
+        let mut action = Action::parse(action_name, action_condition)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        if let Some(segmenter) = segmenter {
+            action = action.with_segmenter(Arc::new(extract_segmenter(segmenter)?));
+        }
+        Ok(PyAction::from(action))
+    }
+
+    #[getter]
+    fn action_name(&self) -> &'static str {
+        self.action.kind().as_str()
+    }
+
+    #[getter]
+    fn action_condition(&self) -> &'static str {
+        self.action.condition().as_str()
     }
 
     #[pyo3(text_signature = "(self, token)")]
-    fn tag(&self, token: &mut PyToken) -> () {
-        self.action.tag(&mut token.token)
+    fn remove(&self, token: &mut PyToken) -> PyResult<()> {
+        token.set_value("".to_string())
     }
 
     #[pyo3(text_signature = "(self, token)")]
-    fn demojize(&self, token: &mut PyToken) -> () {
-        self.action.demojize(&mut token.token)
+    fn tag(&self, token: &mut PyToken) -> PyResult<()> {
+        token.with_token_mut(|token| self.action.tag(token))
     }
 
     #[pyo3(text_signature = "(self, token)")]
-    fn emojize(&self, token: &mut PyToken) -> () {
-        self.action.emojize(&mut token.token)
+    fn demojize(&self, token: &mut PyToken) -> PyResult<()> {
+        token.with_token_mut(|token| self.action.demojize(token))
     }
 
-    fn is_action_valid(&self) -> bool {
-        self.action.is_action_valid()
+    #[pyo3(text_signature = "(self, token)")]
+    fn emojize(&self, token: &mut PyToken) -> PyResult<()> {
+        token.with_token_mut(|token| self.action.emojize(token))
     }
 
     #[pyo3(text_signature = "(self, token)")]
-    pub fn apply(&self, token: &mut PyToken) -> bool {
-        self.action.apply(&mut token.token)
+    pub fn apply(&self, token: &mut PyToken) -> PyResult<bool> {
+        token.with_token_mut(|token| self.action.try_apply(token))?.map_err(to_py_err)
     }
 }